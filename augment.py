@@ -0,0 +1,618 @@
+import collections
+import re
+
+import segment
+from synth import AUX_WORDS
+from synth import load_entity_list  # gazetteers share the entity-list TSV format
+from transforms import extract_insertion
+from transforms import shift_offset
+
+# Augmentation transforms for qabuild. Each transform takes flattened examples
+# (see qa_data.py) and returns an OrderedDict of newly created variant examples
+# with suffixed ids; the originals are never modified in place.
+
+
+# This function locates the final sentence of a context, which is where synth
+# (and AddSent-style dumps) place the distractor. Returns a (start, end) span.
+def _distractor_span(context):
+    spans = segment.split_sentences(context)
+    if spans:
+        return spans[-1]
+    return 0, len(context.rstrip())
+
+
+# This function finds occurrences of gazetteer entities (with word boundaries)
+# in a piece of text, longest entity first so that e.g. "New York City" wins
+# over "New York". Returns a list of (start, end, entity, type) tuples.
+def _find_entities(text, gazetteer):
+    all_entities = []
+    for ent_type, ents in gazetteer.items():
+        for entity in ents:
+            all_entities.append((entity, ent_type))
+    all_entities.sort(key=lambda e: -len(e[0]))
+
+    found = []
+    claimed = [False] * len(text)
+    for entity, ent_type in all_entities:
+        for m in re.finditer(r'\b' + re.escape(entity) + r'\b', text):
+            if any(claimed[m.start():m.end()]):
+                continue
+            for i in range(m.start(), m.end()):
+                claimed[i] = True
+            found.append((m.start(), m.end(), entity, ent_type))
+    found.sort()
+    return found
+
+
+# Gazetteer-based named-entity perturbation (applies to the distractor
+# sentence only). For every entity found in the distractor, up to
+# `num_variants` alternatives of the same type are swapped in, each producing
+# a new adversarial variant example. Alternatives matching the example's gold
+# answers are never used, so the distractor stays a distractor.
+def gazetteer_perturb_examples(examples, gazetteer, num_variants, rng):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    variants = collections.OrderedDict()
+    for example in examples:
+        context = example['context']
+        span_start, span_end = _distractor_span(context)
+        distractor = context[span_start:span_end]
+
+        # Only perturb appended distractors: editing text before an answer span
+        # would invalidate its offsets.
+        if any(a['answer_start'] >= span_start for a in example['answers']):
+            continue
+
+        gold_answers = set(a['text'].lower() for a in example['answers'])
+        counter = 0
+        for ent_start, ent_end, entity, ent_type in _find_entities(distractor, gazetteer):
+            alternatives = [e for e in gazetteer[ent_type]
+                            if e != entity and e.lower() not in gold_answers]
+            rng.shuffle(alternatives)
+            for alternative in alternatives[:num_variants]:
+                counter += 1
+                new_distractor = distractor[:ent_start] + alternative + distractor[ent_end:]
+                new_example = dict(example)
+                new_example['id'] = '{}-gaz{}'.format(example['id'], counter)
+                new_example['context'] = context[:span_start] + new_distractor + context[span_end:]
+                new_example['answers'] = [dict(a) for a in example['answers']]
+                variants[new_example['id']] = new_example
+    return variants
+
+
+# Numeric perturbation of distractor sentences. Numbers and years found in the
+# distractor are perturbed by +/-delta and by swapping adjacent digits, since
+# number confusion is a common model failure mode worth stress-testing. Each
+# distinct perturbed number yields one variant example.
+def number_perturb_examples(examples, delta, num_variants, rng):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    variants = collections.OrderedDict()
+    for example in examples:
+        context = example['context']
+        span_start, span_end = _distractor_span(context)
+        distractor = context[span_start:span_end]
+        if any(a['answer_start'] >= span_start for a in example['answers']):
+            continue
+
+        gold_answers = set(a['text'] for a in example['answers'])
+        counter = 0
+        for m in re.finditer(r'\b\d+\b', distractor):
+            number = m.group()
+            candidates = [str(int(number) + delta), str(int(number) - delta)]
+            # Adjacent-digit swaps (e.g. 1944 -> 9144, 1494, 1949 minus no-ops).
+            for i in range(len(number) - 1):
+                swapped = (number[:i] + number[i + 1] + number[i] + number[i + 2:])
+                candidates.append(swapped)
+            # Deduplicate, drop no-ops, negatives, and anything equal to a gold answer.
+            seen = set()
+            candidates = [c for c in candidates
+                          if not (c in seen or seen.add(c))
+                          and c != number and not c.startswith('-')
+                          and c not in gold_answers]
+            rng.shuffle(candidates)
+            for candidate in candidates[:num_variants]:
+                counter += 1
+                new_distractor = distractor[:m.start()] + candidate + distractor[m.end():]
+                new_example = dict(example)
+                new_example['id'] = '{}-num{}'.format(example['id'], counter)
+                new_example['context'] = context[:span_start] + new_distractor + context[span_end:]
+                new_example['answers'] = [dict(a) for a in example['answers']]
+                variants[new_example['id']] = new_example
+    return variants
+
+
+# Negation-insertion augmentation. The appended distractor sentence gets its
+# polarity flipped: "not"/"never" is removed if present, otherwise "not" is
+# inserted after the first auxiliary (or "never" before the final word when no
+# auxiliary exists). The flipped distractors form a separate challenge set for
+# probing whether models notice negation at all.
+def negation_flip_examples(examples):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    variants = collections.OrderedDict()
+    for example in examples:
+        context = example['context']
+        span_start, span_end = _distractor_span(context)
+        distractor = context[span_start:span_end]
+        if any(a['answer_start'] >= span_start for a in example['answers']):
+            continue
+
+        tokens = distractor.split()
+        lower = [t.lower().strip('.,') for t in tokens]
+        if 'not' in lower or 'never' in lower:
+            # Remove the first negation token.
+            drop = lower.index('not') if 'not' in lower else lower.index('never')
+            flipped_tokens = tokens[:drop] + tokens[drop + 1:]
+        else:
+            aux_index = None
+            for i, t in enumerate(lower):
+                if t in AUX_WORDS:
+                    aux_index = i
+                    break
+            if aux_index is not None:
+                flipped_tokens = tokens[:aux_index + 1] + ['not'] + tokens[aux_index + 1:]
+            elif len(tokens) >= 2:
+                flipped_tokens = tokens[:-1] + ['never'] + tokens[-1:]
+            else:
+                continue
+
+        new_example = dict(example)
+        new_example['id'] = '{}-neg'.format(example['id'])
+        new_example['context'] = (context[:span_start]
+                                  + ' '.join(flipped_tokens)
+                                  + context[span_end:])
+        new_example['answers'] = [dict(a) for a in example['answers']]
+        variants[new_example['id']] = new_example
+    return variants
+
+
+# This function loads a synonym lexicon from a TSV file with lines of the form
+# "word<TAB>synonym[<TAB>synonym...]". Returns a dict mapping lowercased word
+# -> list of synonyms.
+def load_synonym_lexicon(path):
+    lexicon = {}
+    with open(path, encoding='utf-8') as f:
+        for line in f:
+            parts = line.rstrip('\n').split('\t')
+            if len(parts) < 2 or not parts[0]:
+                continue
+            lexicon.setdefault(parts[0].lower(), []).extend(p for p in parts[1:] if p)
+    return lexicon
+
+
+# Synonym-replacement augmentation. Question tokens found in the lexicon are
+# replaced by a random synonym at the given rate, producing paraphrase-ish
+# variants for robustness training. Tokens matching a gold answer are never
+# replaced (and contexts are untouched), so answer spans stay valid.
+def synonym_replace_examples(examples, lexicon, rate, rng):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    variants = collections.OrderedDict()
+    for example in examples:
+        gold_answers = set(a['text'].lower() for a in example['answers'])
+        tokens = example['question'].split()
+        replaced = 0
+        new_tokens = []
+        for token in tokens:
+            # Strip punctuation for lookup but splice it back afterwards.
+            core = token.strip('.,?!;:"\'')
+            synonyms = lexicon.get(core.lower())
+            if (synonyms and core.lower() not in gold_answers
+                    and rng.random() < rate):
+                synonym = rng.choice(synonyms)
+                if core[:1].isupper():
+                    synonym = synonym[:1].upper() + synonym[1:]
+                prefix_len = token.index(core)
+                token = token[:prefix_len] + synonym + token[prefix_len + len(core):]
+                replaced += 1
+            new_tokens.append(token)
+        if replaced == 0:
+            continue
+
+        new_example = dict(example)
+        new_example['id'] = '{}-syn'.format(example['id'])
+        new_example['question'] = ' '.join(new_tokens)
+        new_example['answers'] = [dict(a) for a in example['answers']]
+        variants[new_example['id']] = new_example
+    return variants
+
+
+# Keyboard adjacency used for realistic substitution typos (QWERTY rows).
+_KEYBOARD_ROWS = ['qwertyuiop', 'asdfghjkl', 'zxcvbnm']
+_KEYBOARD_NEIGHBORS = {}
+for _row_index, _row in enumerate(_KEYBOARD_ROWS):
+    for _col, _ch in enumerate(_row):
+        neighbors = []
+        if _col > 0:
+            neighbors.append(_row[_col - 1])
+        if _col + 1 < len(_row):
+            neighbors.append(_row[_col + 1])
+        for _other in (_row_index - 1, _row_index + 1):
+            if 0 <= _other < len(_KEYBOARD_ROWS) and _col < len(_KEYBOARD_ROWS[_other]):
+                neighbors.append(_KEYBOARD_ROWS[_other][_col])
+        _KEYBOARD_NEIGHBORS[_ch] = neighbors
+
+
+# This function injects character noise into a string: adjacent-character
+# swaps, keyboard-adjacent substitutions, and (when allow_drops) character
+# drops, each applied per-letter with the given probability. `protected` is a
+# list of (start, end) spans that must not be touched (answer spans); drops are
+# disabled automatically if any protected span exists, so offsets stay valid.
+def _inject_noise(text, rate, rng, protected=(), allow_drops=True):
+    if protected:
+        allow_drops = False
+    chars = list(text)
+    i = 0
+    out = []
+    while i < len(chars):
+        ch = chars[i]
+        in_protected = any(s <= i < e for s, e in protected)
+        if not in_protected and ch.isalpha() and rng.random() < rate:
+            op_choices = ['swap', 'sub'] + (['drop'] if allow_drops else [])
+            op = rng.choice(op_choices)
+            next_protected = any(s <= i + 1 < e for s, e in protected)
+            if op == 'swap' and i + 1 < len(chars) and chars[i + 1].isalpha() \
+                    and not next_protected:
+                out.append(chars[i + 1])
+                out.append(ch)
+                i += 2
+                continue
+            elif op == 'sub':
+                neighbors = _KEYBOARD_NEIGHBORS.get(ch.lower())
+                if neighbors:
+                    sub = rng.choice(neighbors)
+                    out.append(sub.upper() if ch.isupper() else sub)
+                    i += 1
+                    continue
+            elif op == 'drop':
+                i += 1
+                continue
+        out.append(ch)
+        i += 1
+    return ''.join(out)
+
+
+# Typo/noise injection augmentation. Character noise is applied to the question
+# and/or the context (sparing answer spans, and using only length-preserving
+# operations on contexts so answer offsets survive). One noisy variant is
+# produced per example.
+def typo_noise_examples(examples, rate, rng, target='question'):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    variants = collections.OrderedDict()
+    for example in examples:
+        new_example = dict(example)
+        new_example['answers'] = [dict(a) for a in example['answers']]
+        changed = False
+
+        if target in ('question', 'both'):
+            noisy = _inject_noise(example['question'], rate, rng)
+            if noisy != example['question']:
+                new_example['question'] = noisy
+                changed = True
+        if target in ('context', 'both'):
+            spans = [(a['answer_start'], a['answer_start'] + len(a['text']))
+                     for a in example['answers']]
+            noisy = _inject_noise(example['context'], rate, rng, protected=spans)
+            if noisy != example['context']:
+                new_example['context'] = noisy
+                changed = True
+        if not changed:
+            continue
+
+        new_example['id'] = '{}-typo'.format(example['id'])
+        variants[new_example['id']] = new_example
+    return variants
+
+
+# Function words eligible for token dropout. Content words are never dropped:
+# the point is to produce under-specified questions, not unrelated ones.
+FUNCTION_WORDS = set('''
+a an the of in on at to for from by with about as into over after before
+is are was were be been being do does did has have had
+and or but that which who whom whose this these those it its
+'''.split())
+
+
+# Token-dropout augmentation for questions. Function words are dropped
+# independently with the given probability (tokens in `protected` never are),
+# yielding under-specified question variants for robustness experiments.
+def token_dropout_examples(examples, rate, rng, protected=()):
+    if isinstance(examples, dict):
+        examples = examples.values()
+    protected = set(t.lower() for t in protected)
+
+    variants = collections.OrderedDict()
+    for example in examples:
+        tokens = example['question'].split()
+        kept = []
+        dropped = 0
+        for token in tokens:
+            core = token.strip('.,?!;:"\'').lower()
+            if (core in FUNCTION_WORDS and core not in protected
+                    and rng.random() < rate):
+                dropped += 1
+                continue
+            kept.append(token)
+        if dropped == 0 or not kept:
+            continue
+
+        new_example = dict(example)
+        new_example['id'] = '{}-drop'.format(example['id'])
+        new_example['question'] = ' '.join(kept)
+        new_example['answers'] = [dict(a) for a in example['answers']]
+        variants[new_example['id']] = new_example
+    return variants
+
+
+# Built-in paraphrase templates: (pattern, replacement) regex pairs applied
+# case-insensitively to the whole question. The defaults cover wh-movement
+# ("Who was X?" -> "X was who?") and a few reframings; project-specific rule
+# files extend or replace them.
+PARAPHRASE_RULES = (
+    (r'^(who|what|when|where) (was|is|were|are|did) (.+)\?$', r'\3 \2 \1?'),
+    (r'^(?:what|which) (.+) (was|is|were|are) (.+)\?$',
+     r'Name the \1 that \2 \3.'),
+    (r'^how many (.+)\?$', r'What number of \1?'),
+    (r'^why (did|was|is|does) (.+)\?$', r'For what reason \1 \2?'),
+)
+
+
+# This function loads paraphrase rules from a TSV file with lines of the
+# form "pattern<TAB>replacement" (blank lines and #-comments skipped).
+def load_paraphrase_rules(path):
+    rules = []
+    with open(path, encoding='utf-8') as f:
+        for line in f:
+            line = line.rstrip('\n')
+            if not line.strip() or line.startswith('#'):
+                continue
+            parts = line.split('\t')
+            if len(parts) >= 2:
+                rules.append((parts[0], parts[1]))
+    return rules
+
+
+# Template-based question paraphrasing. Every rule that rewrites the
+# question yields one variant linked to the original id ("-para1", ...);
+# with a synonym lexicon, each rewrite (and the raw question) additionally
+# gets its lexicon words swapped as in synonym_replace_examples, covering
+# questions no template matches. Contexts and answers are untouched, so the
+# variants probe question-side robustness only.
+def paraphrase_examples(examples, rules, num_variants, rng, lexicon=None,
+                        rate=0.3):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    variants = collections.OrderedDict()
+    for example in examples:
+        question = example['question']
+        # Rules see the question with its leading capital lowered, so a moved
+        # wh-word lands mid-sentence in lowercase; the rewrite is then
+        # re-capitalized. Inner proper nouns keep their case.
+        lowered = question[:1].lower() + question[1:]
+        candidates = []
+        for pattern, replacement in rules:
+            rewritten = re.sub(pattern, replacement, lowered,
+                               flags=re.IGNORECASE)
+            if rewritten != lowered and rewritten not in candidates:
+                candidates.append(rewritten[:1].upper() + rewritten[1:])
+        if lexicon:
+            gold_answers = set(a['text'].lower() for a in example['answers'])
+            for base in [question] + list(candidates):
+                tokens = base.split()
+                replaced = 0
+                new_tokens = []
+                for token in tokens:
+                    core = token.strip('.,?!;:"\'')
+                    synonyms = lexicon.get(core.lower())
+                    if (synonyms and core.lower() not in gold_answers
+                            and rng.random() < rate):
+                        synonym = rng.choice(synonyms)
+                        if core[:1].isupper():
+                            synonym = synonym[:1].upper() + synonym[1:]
+                        prefix_len = token.index(core)
+                        token = (token[:prefix_len] + synonym
+                                 + token[prefix_len + len(core):])
+                        replaced += 1
+                    new_tokens.append(token)
+                swapped = ' '.join(new_tokens)
+                if replaced and swapped not in candidates:
+                    candidates.append(swapped)
+
+        for counter, candidate in enumerate(candidates[:num_variants], 1):
+            new_example = dict(example)
+            new_example['id'] = '{}-para{}'.format(example['id'], counter)
+            new_example['question'] = candidate
+            new_example['answers'] = [dict(a) for a in example['answers']]
+            variants[new_example['id']] = new_example
+    return variants
+
+
+# Distractor transplantation. The inserted sentence of each adversarial
+# variant (recovered with extract_insertion against its base context) is
+# harvested and re-inserted into the other contexts of the same title,
+# multiplying the adversarial coverage of a limited AddSent dump. A
+# distractor is never transplanted into a context whose gold answer text it
+# contains (the sentence could otherwise become a spuriously valid span),
+# into the context it came from, or into a context that already contains
+# it. Returns new "-transplantN" variant examples.
+def transplant_distractor_examples(clean, adversarial, rng,
+                                   max_per_example=1, position='append'):
+    harvested = collections.OrderedDict()
+    for variant in adversarial.values():
+        candidate = variant['id']
+        while candidate not in clean and '-' in candidate:
+            candidate = candidate.rsplit('-', 1)[0]
+        if candidate not in clean:
+            continue
+        base = clean[candidate]
+        span = extract_insertion(base['context'], variant['context'])
+        if span is None:
+            continue
+        sentence = variant['context'][span[0]:span[1]].strip()
+        if sentence:
+            pool = harvested.setdefault(base['title'], [])
+            if all(sentence != existing for existing, _ in pool):
+                pool.append((sentence, base['context']))
+
+    variants = collections.OrderedDict()
+    for example in clean.values():
+        context = example['context']
+        gold_answers = [a['text'].lower() for a in example['answers']]
+        pool = [sentence for sentence, source
+                in harvested.get(example['title'], [])
+                if source != context and sentence not in context
+                and not any(answer in sentence.lower()
+                            for answer in gold_answers)]
+        rng.shuffle(pool)
+        for counter, sentence in enumerate(pool[:max_per_example], 1):
+            new_example = dict(example)
+            new_example['id'] = '{}-transplant{}'.format(
+                example['id'], counter)
+            if position == 'prepend':
+                inserted = sentence + ' '
+                new_example['context'] = inserted + context
+                new_example['answers'] = [
+                    {'text': a['text'],
+                     'answer_start': shift_offset(a['answer_start'], 0,
+                                                  len(inserted))}
+                    for a in example['answers']]
+            else:
+                new_example['context'] = context.rstrip() + ' ' + sentence
+                new_example['answers'] = [dict(a)
+                                          for a in example['answers']]
+            variants[new_example['id']] = new_example
+    return variants
+
+
+# Counterfactual entity-swap contrast sets (Gardner et al. 2020 style). The
+# gold answer entity is swapped, at every occurrence in the context, for
+# another entity of the same gazetteer type, and the gold answer is updated
+# to the new entity so the (context, question, answer) triple stays
+# consistent. Examples are skipped when the answer isn't a known entity,
+# when its span doesn't line up with a word-boundary occurrence, or when
+# the entity also appears in the question (swapping would make the question
+# wrong). Returns "-contrastN" variant examples.
+def contrast_swap_examples(examples, gazetteer, num_variants, rng):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    variants = collections.OrderedDict()
+    for example in examples:
+        if example.get('is_impossible') or not example['answers']:
+            continue
+        texts = set(a['text'] for a in example['answers'])
+        if len(texts) != 1:
+            continue
+        entity = texts.pop()
+
+        ent_type = None
+        for candidate_type, ents in gazetteer.items():
+            if entity in ents:
+                ent_type = candidate_type
+                break
+        if ent_type is None:
+            continue
+
+        pattern = re.compile(r'\b' + re.escape(entity) + r'\b')
+        if pattern.search(example['question']):
+            continue
+        context = example['context']
+        occurrences = [m.start() for m in pattern.finditer(context)]
+        if any(a['answer_start'] not in occurrences
+               for a in example['answers']):
+            continue
+
+        # Alternatives already present in the context would leave two
+        # plausible spans, so they are never used.
+        alternatives = [e for e in gazetteer[ent_type]
+                        if e != entity and not
+                        re.search(r'\b' + re.escape(e) + r'\b', context)]
+        rng.shuffle(alternatives)
+        for counter, alternative in enumerate(alternatives[:num_variants], 1):
+            delta = len(alternative) - len(entity)
+            new_example = dict(example)
+            new_example['id'] = '{}-contrast{}'.format(example['id'], counter)
+            new_example['context'] = pattern.sub(alternative, context)
+            new_example['answers'] = [
+                {'text': alternative,
+                 'answer_start': a['answer_start'] + delta * sum(
+                     1 for o in occurrences if o < a['answer_start'])}
+                for a in example['answers']]
+            variants[new_example['id']] = new_example
+    return variants
+
+
+# The casing perturbations case_perturb_examples knows how to apply.
+CASE_MODES = ('lower', 'upper', 'random')
+
+
+# Casing robustness transform: lowercased, uppercased, and randomly-cased
+# variants of questions (and optionally contexts) for probing how much
+# cased models lean on capitalization. Re-casing never changes lengths, so
+# offsets survive; answer texts are re-read from the re-cased context so
+# spans keep matching. Returns "-case<mode>" variant examples.
+def case_perturb_examples(examples, modes, rng, target='question'):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    def recase(text, mode):
+        if mode == 'lower':
+            return text.lower()
+        if mode == 'upper':
+            return text.upper()
+        return ''.join(ch.upper() if rng.random() < 0.5 else ch.lower()
+                       for ch in text)
+
+    variants = collections.OrderedDict()
+    for example in examples:
+        for mode in modes:
+            if mode not in CASE_MODES:
+                raise ValueError('Unrecognized casing mode: {}'.format(mode))
+            new_example = dict(example)
+            new_example['id'] = '{}-case{}'.format(example['id'], mode)
+            if target in ('question', 'both'):
+                new_example['question'] = recase(example['question'], mode)
+            if target in ('context', 'both'):
+                new_context = recase(example['context'], mode)
+                new_example['context'] = new_context
+                new_example['answers'] = [
+                    {'text': new_context[a['answer_start']:
+                                         a['answer_start'] + len(a['text'])],
+                     'answer_start': a['answer_start']}
+                    for a in example['answers']]
+            else:
+                new_example['answers'] = [dict(a)
+                                          for a in example['answers']]
+            variants[new_example['id']] = new_example
+    return variants
+
+
+# Back-translation variants. The actual round-trip translation happens
+# outside this module (an external command or API; see the --backtranslate
+# plumbing in qabuild.py) — this transform just joins its output back on:
+# `paraphrases` maps original question -> round-tripped question, and every
+# example whose paraphrase survived the round trip with an actual change
+# yields one "-bt" variant.
+def backtranslate_examples(examples, paraphrases):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    variants = collections.OrderedDict()
+    for example in examples:
+        paraphrase = (paraphrases.get(example['question']) or '').strip()
+        if not paraphrase or paraphrase == example['question']:
+            continue
+        new_example = dict(example)
+        new_example['id'] = '{}-bt'.format(example['id'])
+        new_example['question'] = paraphrase
+        new_example['answers'] = [dict(a) for a in example['answers']]
+        variants[new_example['id']] = new_example
+    return variants
@@ -0,0 +1,85 @@
+import collections
+import csv
+
+# Exporters that turn qabuild datasets into downstream training formats.
+# Imports of the training stack (numpy, transformers) are deferred into the
+# functions that need them so the rest of qabuild works without those packages.
+
+
+# This function converts flattened examples (see qa_data.py) into the batched
+# column format used by helpers.py / HF datasets: a dict of parallel lists,
+# with answers as {'text': [...], 'answer_start': [...]}.
+def examples_to_batch(examples):
+    if isinstance(examples, dict):
+        examples = examples.values()
+    batch = collections.defaultdict(list)
+    for example in examples:
+        batch['id'].append(example['id'])
+        batch['title'].append(example['title'])
+        batch['context'].append(example['context'])
+        batch['question'].append(example['question'])
+        batch['answers'].append({
+            'text': [a['text'] for a in example['answers']],
+            'answer_start': [a['answer_start'] for a in example['answers']],
+        })
+    return dict(batch)
+
+
+# This function runs the same QA preprocessing as training (helpers.py's
+# prepare_train_dataset_qa) and writes the resulting features (input_ids,
+# attention_mask, token_type_ids, start/end positions) to an .npz file, so the
+# Python training side can skip its slowest preprocessing step entirely.
+# `tokenizer_name` must be a HuggingFace model ID or checkpoint directory with
+# a fast tokenizer.
+def export_training_features(examples, tokenizer_name, path):
+    import numpy as np
+    from transformers import AutoTokenizer
+    from helpers import prepare_train_dataset_qa
+
+    tokenizer = AutoTokenizer.from_pretrained(tokenizer_name, use_fast=True)
+    batch = examples_to_batch(examples)
+    features = prepare_train_dataset_qa(batch, tokenizer)
+
+    arrays = {
+        'input_ids': np.asarray(features['input_ids'], dtype=np.int64),
+        'attention_mask': np.asarray(features['attention_mask'], dtype=np.int64),
+        'start_positions': np.asarray(features['start_positions'], dtype=np.int64),
+        'end_positions': np.asarray(features['end_positions'], dtype=np.int64),
+    }
+    if 'token_type_ids' in features:
+        arrays['token_type_ids'] = np.asarray(features['token_type_ids'], dtype=np.int64)
+    np.savez_compressed(path, **arrays)
+    return len(arrays['input_ids'])
+
+
+# This function writes examples as MTurk-style CSV batches for
+# re-annotation, one row per HIT with columns qabuild_id, title, question,
+# context, answer_text, answer_start. qabuild_id carries the original
+# example id so annotations can be joined back onto the dataset; rows with
+# several gold answers repeat the id with an answer index appended
+# ("q123#1"), which importers strip at the '#'. Batches are written as
+# "<stem>-batch-001.csv" etc. Returns the list of files written.
+def export_hit_batches(examples, stem, batch_size):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    rows = []
+    for example in examples:
+        answers = example['answers'] or [{'text': '', 'answer_start': -1}]
+        for index, answer in enumerate(answers):
+            hit_id = example['id'] if len(answers) == 1 \
+                else '{}#{}'.format(example['id'], index)
+            rows.append([hit_id, example['title'], example['question'],
+                         example['context'], answer['text'],
+                         answer['answer_start']])
+
+    paths = []
+    for start in range(0, len(rows), batch_size):
+        path = '{}-batch-{:03d}.csv'.format(stem, len(paths) + 1)
+        with open(path, encoding='utf-8', mode='w', newline='') as f:
+            writer = csv.writer(f)
+            writer.writerow(['qabuild_id', 'title', 'question', 'context',
+                             'answer_text', 'answer_start'])
+            writer.writerows(rows[start:start + batch_size])
+        paths.append(path)
+    return paths
@@ -0,0 +1,193 @@
+import collections
+import csv
+import json
+import os
+
+# Importers that convert other QA dataset formats into qabuild's internal
+# flattened example representation (see qa_data.py), so the same adversarial
+# pipeline can run on more than SQuAD-family inputs.
+
+
+# This function imports the Natural Questions "simplified" JSONL format. The
+# document is a whitespace-tokenized string with HTML-ish tokens mixed in; we
+# take the annotated long answer as the context (HTML tokens stripped) and map
+# short answers to character spans within it. Examples with a long answer but
+# no short answer become unanswerable; examples without a long answer are
+# skipped.
+def import_nq_jsonl(path):
+    examples = collections.OrderedDict()
+    with open(path, encoding='utf-8') as f:
+        for line in f:
+            if not line.strip():
+                continue
+            record = json.loads(line)
+            annotation = record['annotations'][0] if record.get('annotations') else None
+            if annotation is None:
+                continue
+            long_answer = annotation.get('long_answer', {})
+            if long_answer.get('start_token', -1) < 0:
+                continue
+
+            tokens = record['document_text'].split(' ')
+            la_start, la_end = long_answer['start_token'], long_answer['end_token']
+
+            # Build the context from non-HTML tokens, remembering the character
+            # offset of each original token index so short answers can be mapped.
+            parts = []
+            token_offsets = {}
+            pos = 0
+            for i in range(la_start, la_end):
+                token = tokens[i]
+                if token.startswith('<') and token.endswith('>'):
+                    continue
+                token_offsets[i] = pos
+                parts.append(token)
+                pos += len(token) + 1
+            context = ' '.join(parts)
+
+            answers = []
+            for short in annotation.get('short_answers', []):
+                sa_start, sa_end = short['start_token'], short['end_token']
+                words = [tokens[i] for i in range(sa_start, sa_end)
+                         if i in token_offsets]
+                if not words or sa_start not in token_offsets:
+                    continue
+                answers.append({'text': ' '.join(words),
+                                'answer_start': token_offsets[sa_start]})
+
+            example = {
+                'id': str(record['example_id']),
+                'title': record.get('document_title', ''),
+                'context': context,
+                'question': record['question_text'],
+                'answers': answers,
+            }
+            if not answers:
+                example['is_impossible'] = True
+            examples[example['id']] = example
+    return examples
+
+
+# This function imports TriviaQA-format JSON with distant supervision: for
+# each question, the evidence files referenced by EntityPages/SearchResults
+# are scanned (from evidence_dir) for the first occurrence of any answer
+# alias, which becomes the answer span. Questions whose aliases appear in no
+# evidence document are skipped. One example is produced per (question,
+# evidence document) pair, suffixed with the document index.
+def import_triviaqa(path, evidence_dir):
+    with open(path, encoding='utf-8') as f:
+        raw = json.load(f)
+
+    examples = collections.OrderedDict()
+    for item in raw['Data']:
+        aliases = item['Answer'].get('Aliases', [])
+        aliases = sorted(aliases, key=len, reverse=True)
+        pages = (item.get('EntityPages', []) or []) + (item.get('SearchResults', []) or [])
+        for doc_index, page in enumerate(pages):
+            filename = page.get('Filename')
+            if not filename:
+                continue
+            evidence_path = os.path.join(evidence_dir, filename)
+            if not os.path.exists(evidence_path):
+                continue
+            with open(evidence_path, encoding='utf-8') as ef:
+                text = ef.read()
+            lowered = text.lower()
+
+            found = None
+            for alias in aliases:
+                start = lowered.find(alias.lower())
+                if start != -1:
+                    found = {'text': text[start:start + len(alias)],
+                             'answer_start': start}
+                    break
+            if found is None:
+                continue
+
+            example_id = '{}--{}'.format(item['QuestionId'], doc_index)
+            examples[example_id] = {
+                'id': example_id,
+                'title': page.get('Title', ''),
+                'context': text,
+                'question': item['Question'],
+                'answers': [found],
+            }
+    return examples
+
+
+# This function imports NewsQA. Both the combined JSON form (story text plus
+# per-question consensus spans) and the CSV form (answer_char_ranges columns)
+# are supported. With validated_only, questions lacking a consensus/validated
+# answer are dropped rather than emitted as unanswerable.
+def import_newsqa(path, validated_only=False):
+    if path.endswith('.csv'):
+        return _import_newsqa_csv(path, validated_only)
+    return _import_newsqa_json(path, validated_only)
+
+
+def _import_newsqa_json(path, validated_only):
+    with open(path, encoding='utf-8') as f:
+        raw = json.load(f)
+
+    examples = collections.OrderedDict()
+    for story in raw['data']:
+        text = story['text']
+        title = story.get('storyId', '')
+        for q_index, question in enumerate(story.get('questions', [])):
+            consensus = question.get('consensus', {})
+            example_id = '{}--{}'.format(title, q_index)
+            if 's' in consensus and 'e' in consensus:
+                start, end = consensus['s'], consensus['e']
+                answers = [{'text': text[start:end].rstrip(),
+                            'answer_start': start}]
+            else:
+                # badQuestion / noAnswer consensus.
+                if validated_only:
+                    continue
+                answers = []
+            example = {
+                'id': example_id,
+                'title': title,
+                'context': text,
+                'question': question['q'],
+                'answers': answers,
+            }
+            if not answers:
+                example['is_impossible'] = True
+            examples[example_id] = example
+    return examples
+
+
+# The CSV form has one row per question with columns story_id, story_text,
+# question, and answer_char_ranges ("start:end" spans, '|'-separated per
+# annotator, "None" for no answer); is_answer_absent/is_question_bad flag bad
+# rows. The first annotator span is used (majority handling belongs to the
+# multi-answer policies, not the importer).
+def _import_newsqa_csv(path, validated_only):
+    examples = collections.OrderedDict()
+    with open(path, encoding='utf-8', newline='') as f:
+        for row_index, row in enumerate(csv.DictReader(f)):
+            text = row.get('story_text', '')
+            spans = []
+            for annotator in row.get('answer_char_ranges', '').split('|'):
+                for piece in annotator.split(','):
+                    piece = piece.strip()
+                    if piece and piece != 'None' and ':' in piece:
+                        start, end = piece.split(':', 1)
+                        spans.append((int(start), int(end)))
+            answers = [{'text': text[s:e].rstrip(), 'answer_start': s}
+                       for s, e in spans[:1]]
+            if validated_only and not answers:
+                continue
+            example_id = '{}--{}'.format(row.get('story_id', ''), row_index)
+            example = {
+                'id': example_id,
+                'title': row.get('story_id', ''),
+                'context': text,
+                'question': row.get('question', ''),
+                'answers': answers,
+            }
+            if not answers:
+                example['is_impossible'] = True
+            examples[example_id] = example
+    return examples
@@ -0,0 +1,222 @@
+import collections
+import hashlib
+import json
+import os
+import sys
+import time
+
+# Build manifests: every generating run can record what produced its outputs
+# (version, full CLI args, seed, input hashes, output hashes) so experiments
+# stay auditable months later.
+
+# qabuild release version recorded in manifests.
+VERSION = '0.1.0'
+
+# Argument names that hold output paths rather than inputs.
+OUTPUT_ARGS = ('output', 'output_dir')
+
+# Argument names that never name an input file (bookkeeping flags and the
+# run's own report paths).
+NON_INPUT_ARGS = ('func', 'manifest', 'json_summary', 'rejects', 'checkpoint')
+
+
+# This function lists the files a run produced: its output path (including
+# sibling files sharing the stem, for "-epochN"/"-variants.tsv" style
+# outputs) or the contents of its output directory.
+def output_files(args):
+    outputs = []
+    for name in OUTPUT_ARGS:
+        value = getattr(args, name, None)
+        if not isinstance(value, str):
+            continue
+        if os.path.isdir(value):
+            outputs.extend(os.path.join(value, entry)
+                           for entry in sorted(os.listdir(value)))
+        elif os.path.isfile(value):
+            outputs.append(value)
+        else:
+            stem = os.path.splitext(value)[0]
+            directory = os.path.dirname(value) or '.'
+            outputs.extend(
+                entry_path for entry in sorted(os.listdir(directory))
+                for entry_path in [os.path.join(directory, entry)]
+                if entry_path.startswith(stem))
+    return [path for path in outputs if os.path.isfile(path)]
+
+
+# This function computes the SHA-256 hex digest of a file's bytes.
+def file_sha256(path):
+    digest = hashlib.sha256()
+    with open(path, 'rb') as f:
+        for block in iter(lambda: f.read(1 << 20), b''):
+            digest.update(block)
+    return digest.hexdigest()
+
+
+# This function collects every argument value that names an existing file, so
+# entity lists, gazetteers, and score files are hashed alongside the dataset
+# inputs without per-subcommand wiring. Returns {path: sha256}.
+def _hash_file_args(args, names):
+    hashes = collections.OrderedDict()
+    for name in names:
+        value = getattr(args, name)
+        values = value if isinstance(value, list) else [value]
+        for item in values:
+            if isinstance(item, str) and os.path.isfile(item):
+                hashes[item] = file_sha256(item)
+    return hashes
+
+
+# This function reads the provenance chain recorded in a SQuAD-format JSON
+# file; files without one (or non-SQuAD JSON) yield an empty chain.
+def read_provenance(path):
+    try:
+        with open(path, encoding='utf-8') as f:
+            raw = json.load(f)
+    except (OSError, ValueError):
+        return []
+    if not isinstance(raw, dict) or 'data' not in raw:
+        return []
+    return raw.get('provenance', [])
+
+
+# This function chains provenance across pipeline steps: the provenance blocks
+# of every SQuAD-format input named in the args are concatenated, a record for
+# this run is appended, and the chain is written into each SQuAD-format output
+# file. Outputs that are not SQuAD JSON are left untouched, so feeding a
+# qabuild output back in (filter -> mix -> split) accumulates the full list of
+# transforms that produced the final file.
+def chain_provenance(args):
+    chain = []
+    for name, value in sorted(vars(args).items()):
+        if name in OUTPUT_ARGS or name in NON_INPUT_ARGS:
+            continue
+        values = value if isinstance(value, list) else [value]
+        for item in values:
+            if isinstance(item, str) and os.path.isfile(item):
+                chain.extend(read_provenance(item))
+    # No timestamp here, and output paths are reduced to their basenames:
+    # provenance lives inside the output file, so anything that varies between
+    # otherwise-identical rebuilds (wall time, a temp output directory) would
+    # make them differ byte-for-byte and break `qabuild repro`. The run
+    # manifest records the full paths and wall time.
+    output_paths = set()
+    for name in OUTPUT_ARGS + ('manifest',):
+        value = getattr(args, name, None)
+        if isinstance(value, str):
+            output_paths.add(value)
+    argv = [os.path.basename(token.rstrip('/')) if token in output_paths
+            else token for token in sys.argv[1:]]
+    chain.append(collections.OrderedDict([
+        ('tool', 'qabuild'),
+        ('version', VERSION),
+        ('command', getattr(args, 'command', None)),
+        ('argv', argv),
+    ]))
+
+    for path in output_files(args):
+        if not path.endswith('.json'):
+            continue
+        with open(path, encoding='utf-8') as f:
+            raw = json.load(f)
+        if not isinstance(raw, dict) or 'data' not in raw:
+            continue
+        raw['provenance'] = chain
+        with open(path, encoding='utf-8', mode='w') as f:
+            json.dump(raw, f, ensure_ascii=False)
+
+
+# This function verifies a previously written manifest: every input and
+# output file is re-hashed and compared against the recorded SHA-256. Returns
+# a list of (path, status) pairs where status is 'ok', 'changed', or
+# 'missing'.
+def verify_manifest(path):
+    with open(path, encoding='utf-8') as f:
+        record = json.load(f)
+
+    results = []
+    for section in ('inputs', 'outputs'):
+        for file_path, expected in record.get(section, {}).items():
+            if not os.path.isfile(file_path):
+                results.append((file_path, 'missing'))
+            elif file_sha256(file_path) != expected:
+                results.append((file_path, 'changed'))
+            else:
+                results.append((file_path, 'ok'))
+    return results
+
+
+# This function rewrites a recorded argv so every output path (and any nested
+# --manifest path) lands under temp_dir instead, leaving the original build
+# untouched. Returns the rewritten argv.
+def redirect_argv_outputs(record, temp_dir):
+    redirect = set()
+    for name in OUTPUT_ARGS + ('manifest',):
+        value = record['args'].get(name)
+        if isinstance(value, str):
+            redirect.add(value)
+
+    argv = []
+    previous = None
+    for token in record['argv']:
+        # '--manifest' is stripped from the recorded args, so its path is
+        # caught positionally; it must be redirected too or the rebuild would
+        # overwrite the manifest being reproduced.
+        if token in redirect or previous == '--manifest':
+            token = os.path.join(temp_dir, os.path.basename(token.rstrip('/')))
+        previous = token
+        argv.append(token)
+    return argv
+
+
+# This function compares the outputs recorded in a manifest against the files
+# a rebuild produced under temp_dir (matched by basename, searched
+# recursively). Returns a list of (recorded path, status) pairs with status
+# 'ok', 'changed', or 'missing'.
+def compare_rebuild(record, temp_dir):
+    rebuilt = {}
+    for directory, _, filenames in os.walk(temp_dir):
+        for filename in filenames:
+            rebuilt.setdefault(filename, os.path.join(directory, filename))
+
+    results = []
+    for file_path, expected in record.get('outputs', {}).items():
+        candidate = rebuilt.get(os.path.basename(file_path))
+        if candidate is None:
+            results.append((file_path, 'missing'))
+        elif file_sha256(candidate) != expected:
+            results.append((file_path, 'changed'))
+        else:
+            results.append((file_path, 'ok'))
+    return results
+
+
+# This function writes a manifest.json describing a finished qabuild run: the
+# version, command line, seed, SHA-256 of every input file named in the args,
+# and SHA-256 of every output file the run produced (for multi-file outputs,
+# whatever exists under the output directory is included).
+def write_run_manifest(path, args):
+    arg_items = collections.OrderedDict(
+        (name, value) for name, value in sorted(vars(args).items())
+        if name not in NON_INPUT_ARGS)
+
+    input_names = [name for name in arg_items if name not in OUTPUT_ARGS]
+    inputs = _hash_file_args(args, input_names)
+
+    outputs = collections.OrderedDict(
+        (entry_path, file_sha256(entry_path))
+        for entry_path in output_files(args))
+
+    record = collections.OrderedDict([
+        ('qabuild_version', VERSION),
+        ('created', time.strftime('%Y-%m-%dT%H:%M:%S%z')),
+        ('command', getattr(args, 'command', None)),
+        ('argv', sys.argv[1:]),
+        ('args', arg_items),
+        ('seed', getattr(args, 'seed', None)),
+        ('inputs', inputs),
+        ('outputs', outputs),
+    ])
+    with open(path, encoding='utf-8', mode='w') as f:
+        json.dump(record, f, indent=2)
+    return record
@@ -0,0 +1,61 @@
+import collections
+import importlib.util
+import os
+
+# Plugin transforms: small, shareable Python modules dropped into a plugins
+# directory. A plugin module defines
+#     transform(example) -> example | list of examples | None
+# and is applied per example; returning None (or an empty list) drops the
+# example, returning several examples fans it out. Plugins run in-process, so
+# they are for trusted code — the `exec` subcommand is the boundary for
+# anything that needs isolation.
+
+
+# This function lists the plugins available in a directory: every *.py file
+# (ignoring underscore-prefixed ones) keyed by its stem name.
+def discover_plugins(directory):
+    plugins = collections.OrderedDict()
+    for entry in sorted(os.listdir(directory)):
+        if entry.endswith('.py') and not entry.startswith('_'):
+            plugins[entry[:-3]] = os.path.join(directory, entry)
+    return plugins
+
+
+# This function loads a plugin module from its path and returns its
+# transform callable.
+def load_plugin(path):
+    name = 'qabuild_plugin_{}'.format(
+        os.path.splitext(os.path.basename(path))[0])
+    spec = importlib.util.spec_from_file_location(name, path)
+    module = importlib.util.module_from_spec(spec)
+    spec.loader.exec_module(module)
+    if not callable(getattr(module, 'transform', None)):
+        raise ValueError(
+            'plugin {} does not define a transform() function'.format(path))
+    return module.transform
+
+
+# This function applies a plugin transform to every example, verifying the
+# answer offsets of whatever comes back before it is accepted (plugins are as
+# likely as external commands to rot spans). Raises ValueError on a bad span.
+def apply_plugin(examples, transform):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    outputs = collections.OrderedDict()
+    for example in examples:
+        returned = transform(dict(example))
+        if returned is None:
+            continue
+        if isinstance(returned, dict):
+            returned = [returned]
+        for new_example in returned:
+            for answer in new_example['answers']:
+                start = answer['answer_start']
+                text = answer['text']
+                if new_example['context'][start:start + len(text)] != text:
+                    raise ValueError(
+                        '{}: answer {!r} does not match context at offset '
+                        '{}'.format(new_example['id'], text, start))
+            outputs[new_example['id']] = new_example
+    return outputs
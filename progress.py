@@ -0,0 +1,53 @@
+import sys
+import time
+
+# Lightweight progress reporting for long-running operations: a single
+# carriage-return-updated line on stderr with a count and throughput. On by
+# default only when stderr is a terminal; --progress/--no-progress override.
+
+# Tri-state override set from the command line (None = auto-detect).
+ENABLED = None
+
+
+def set_enabled(value):
+    global ENABLED
+    ENABLED = value
+
+
+def _active():
+    if ENABLED is not None:
+        return ENABLED
+    return sys.stderr.isatty()
+
+
+# This generator passes an iterable through while reporting progress as
+# "<label>: <count>[/total] (<rate>/s)" at most every `interval` seconds,
+# finishing with a newline so subsequent output starts clean.
+def track(iterable, label, total=None, interval=0.1):
+    if not _active():
+        yield from iterable
+        return
+
+    start = time.time()
+    count = 0
+    last_report = 0.0
+    for item in iterable:
+        count += 1
+        now = time.time()
+        if now - last_report >= interval:
+            last_report = now
+            _report(label, count, total, now - start)
+        yield item
+    _report(label, count, total, time.time() - start)
+    sys.stderr.write('\n')
+    sys.stderr.flush()
+
+
+def _report(label, count, total, elapsed):
+    rate = count / max(elapsed, 1e-9)
+    if total:
+        sys.stderr.write('\r{}: {}/{} ({:.0f}/s)'.format(
+            label, count, total, rate))
+    else:
+        sys.stderr.write('\r{}: {} ({:.0f}/s)'.format(label, count, rate))
+    sys.stderr.flush()
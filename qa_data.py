@@ -0,0 +1,626 @@
+import codecs
+import collections
+import hashlib
+import json
+import mmap
+
+# qabuild's internal representation of a QA example is a flat dict with keys:
+#   'id', 'title', 'context', 'question',
+#   'answers' (a list of {'text': str, 'answer_start': int} dicts, raw SQuAD style)
+# plus 'is_impossible' when reading SQuAD 2.0-style files.
+# read_raw_examples flattens the nested SQuAD layout into an id-keyed dict, and
+# write_squad_file re-nests examples (grouping by title and context) on the way out.
+
+
+# Internally all offsets are Unicode character (code point) offsets. Some
+# SQuAD-family files (XQuAD/MLQA exports, JS tooling output) count answer_start
+# in UTF-8 bytes or UTF-16 code units instead, which silently corrupts spans on
+# non-ASCII contexts; the conversion helpers below translate on read/write.
+OFFSET_UNITS = ('chars', 'bytes', 'utf16')
+
+
+# Width of one character in the given offset unit.
+def _char_width(ch, unit):
+    if unit == 'bytes':
+        return len(ch.encode('utf-8'))
+    if unit == 'utf16':
+        return 2 if ord(ch) > 0xFFFF else 1
+    return 1
+
+
+# This function converts an offset counted in `unit` into a character offset.
+def to_char_offset(text, offset, unit):
+    if unit == 'chars':
+        return offset
+    pos = 0
+    for i, ch in enumerate(text):
+        if pos == offset:
+            return i
+        pos += _char_width(ch, unit)
+    if pos == offset:
+        return len(text)
+    raise ValueError(
+        '{} offset {} does not fall on a character boundary'.format(unit, offset))
+
+
+# This function converts a character offset into an offset counted in `unit`.
+def from_char_offset(text, char_offset, unit):
+    if unit == 'chars':
+        return char_offset
+    return sum(_char_width(ch, unit) for ch in text[:char_offset])
+
+
+# Lenient parse mode: instead of raising on a paragraph or qa with missing
+# or malformed fields, record it in REJECTS with a reason and keep going, so
+# one bad record cannot abort a long build. Enabled from the command line via
+# --lenient; the CLI writes REJECTS out afterwards.
+LENIENT = False
+REJECTS = []
+
+
+def set_lenient(value):
+    global LENIENT
+    LENIENT = value
+    del REJECTS[:]
+
+
+def _reject(title, qa_id, reason):
+    REJECTS.append(collections.OrderedDict([
+        ('title', title),
+        ('id', qa_id),
+        ('reason', reason),
+    ]))
+
+
+# How duplicate ids within one file are handled (set from --on-duplicate):
+# 'error' aborts naming the offending id and titles, 'keep-first' and
+# 'keep-last' pick a survivor, and 'rename' appends "-dupN" to later copies
+# (the suffix strips like any variant id). The default is 'error' because a
+# silent overwrite is never what a collision meant.
+DUPLICATE_POLICIES = ('error', 'keep-first', 'keep-last', 'rename')
+ON_DUPLICATE = 'error'
+
+
+def set_on_duplicate(policy):
+    global ON_DUPLICATE
+    ON_DUPLICATE = policy
+
+
+# How multi-answer questions are reduced on read (set from --answers):
+# dev-style files carry several gold spans per question, but single-span
+# training wants exactly one. 'all' keeps every span, 'first' the first,
+# 'majority' the text most annotators agreed on, 'longest' the longest span.
+ANSWER_POLICIES = ('all', 'first', 'majority', 'longest')
+ANSWER_POLICY = 'all'
+
+
+def set_answer_policy(policy):
+    global ANSWER_POLICY
+    ANSWER_POLICY = policy
+
+
+# This function reduces a multi-answer list according to the configured
+# policy. Ties under 'majority' break toward the earliest answer so the
+# choice is deterministic.
+def _select_answers(answers):
+    if ANSWER_POLICY == 'all' or len(answers) <= 1:
+        return answers
+    if ANSWER_POLICY == 'first':
+        return answers[:1]
+    if ANSWER_POLICY == 'longest':
+        return [max(answers, key=lambda a: len(a['text']))]
+    counts = collections.Counter(a['text'] for a in answers)
+    best = max(counts.values())
+    winners = set(text for text, count in counts.items() if count == best)
+    return [next(a for a in answers if a['text'] in winners)]
+
+
+# This function inserts an example into an id-keyed dict, applying the
+# duplicate policy when the id is already taken.
+def _insert_example(examples, example):
+    if ANSWER_POLICY != 'all' and len(example.get('answers') or ()) > 1:
+        example = dict(example)
+        example['answers'] = _select_answers(example['answers'])
+    example_id = example['id']
+    if example_id not in examples:
+        examples[example_id] = example
+        return
+    if ON_DUPLICATE == 'keep-first':
+        return
+    if ON_DUPLICATE == 'keep-last':
+        examples[example_id] = example
+        return
+    if ON_DUPLICATE == 'rename':
+        n = 2
+        while '{}-dup{}'.format(example_id, n) in examples:
+            n += 1
+        example = dict(example)
+        example['id'] = '{}-dup{}'.format(example_id, n)
+        examples[example['id']] = example
+        return
+    raise ValueError(
+        'duplicate id {!r} (titles {!r} and {!r}); pass --on-duplicate to '
+        'choose keep-first, keep-last, or rename'.format(
+            example_id, examples[example_id]['title'], example['title']))
+
+
+# This function re-keys synthesized/augmented examples with deterministic
+# hash-based ids: each id gets a suffix derived from sha1(old id, transform,
+# seed). Reruns with the same parameters produce identical ids (so predictions
+# join reliably), different parameters produce disjoint ids, and the base-id
+# prefix is preserved so variant matching by suffix stripping keeps working.
+def hash_variant_ids(examples, transform, seed):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    rekeyed = collections.OrderedDict()
+    for example in examples:
+        key = '{}:{}:{}'.format(example['id'], transform, seed)
+        digest = hashlib.sha1(key.encode('utf-8')).hexdigest()[:8]
+        example = dict(example)
+        example['id'] = '{}-{}'.format(example['id'], digest)
+        rekeyed[example['id']] = example
+    return rekeyed
+
+
+# This function parses SQuAD-format JSON text and flattens it into an
+# OrderedDict mapping example id -> example dict (see module comment for
+# keys). offset_unit declares how answer_start is counted in the text;
+# offsets are always converted to character offsets internally. Parsing is
+# split from file IO so environments without a filesystem (notebooks,
+# browser-hosted inspectors) can feed strings straight in.
+def parse_squad(text, offset_unit='chars'):
+    raw = json.loads(text)
+
+    examples = collections.OrderedDict()
+    for article in raw['data']:
+        for example in _article_examples(article, offset_unit):
+            _insert_example(examples, example)
+    return intern_contexts(examples)
+
+
+# This function interns context strings across examples: every repeated
+# context is replaced by one shared string object, so a flattened corpus
+# where thousands of qas share a paragraph costs one context's worth of
+# memory instead of one per example (transforms that dict()-copy examples
+# keep sharing the same object). Mutates and returns the examples.
+def intern_contexts(examples):
+    values = examples.values() if isinstance(examples, dict) else examples
+    pool = {}
+    for example in values:
+        example['context'] = pool.setdefault(example['context'],
+                                             example['context'])
+    return examples
+
+
+# This function reads a SQuAD-format JSON file and flattens it (see
+# parse_squad).
+def read_raw_examples(path, offset_unit='chars'):
+    with open(path, encoding='utf-8') as f:
+        return parse_squad(f.read(), offset_unit=offset_unit)
+
+
+# This function flattens one qa into an example dict (shared by the strict
+# and lenient article generators).
+def _qa_example(qa, title, context, offset_unit):
+    answers = _select_answers(qa['answers'])
+    if offset_unit != 'chars':
+        answers = [{'text': a['text'],
+                    'answer_start': to_char_offset(
+                        context, a['answer_start'], offset_unit)}
+                   for a in answers]
+    example = {
+        'id': qa['id'],
+        'title': title,
+        'context': context,
+        'question': qa['question'],
+        'answers': answers,
+    }
+    if 'is_impossible' in qa:
+        example['is_impossible'] = qa['is_impossible']
+    return example
+
+
+# This function flattens the qas of one article into example dicts (the
+# per-article core of parse_squad), yielding them in order. In lenient mode
+# malformed entries are recorded and skipped instead.
+def _article_examples(article, offset_unit):
+    if LENIENT:
+        yield from _article_examples_lenient(article, offset_unit)
+        return
+    title = article.get('title', '')
+    for paragraph in article['paragraphs']:
+        context = paragraph['context']
+        for qa in paragraph['qas']:
+            yield _qa_example(qa, title, context, offset_unit)
+
+
+# This function describes what is wrong with a qa dict, or returns None if it
+# is well-formed.
+def _qa_problem(qa):
+    if not isinstance(qa, dict):
+        return 'qa is not an object'
+    for key in ('id', 'question'):
+        if not isinstance(qa.get(key), str):
+            return 'missing or non-string {!r}'.format(key)
+    if not isinstance(qa.get('answers'), list):
+        return 'missing answers list'
+    for answer in qa['answers']:
+        if (not isinstance(answer, dict)
+                or not isinstance(answer.get('text'), str)
+                or not isinstance(answer.get('answer_start'), int)):
+            return 'malformed answer'
+    return None
+
+
+# This generator is the lenient counterpart of _article_examples: articles
+# without a paragraphs list, paragraphs without a context string, and
+# malformed qas go to REJECTS (with a reason) rather than raising.
+def _article_examples_lenient(article, offset_unit):
+    title = article.get('title', '') if isinstance(article, dict) else ''
+    if not isinstance(article, dict) \
+            or not isinstance(article.get('paragraphs'), list):
+        _reject(title, None, 'article has no paragraphs list')
+        return
+    for paragraph in article['paragraphs']:
+        if not isinstance(paragraph, dict) \
+                or not isinstance(paragraph.get('context'), str):
+            _reject(title, None, 'paragraph has no context string')
+            continue
+        context = paragraph['context']
+        qas = paragraph.get('qas')
+        if not isinstance(qas, list):
+            _reject(title, None, 'paragraph has no qas list')
+            continue
+        for qa in qas:
+            reason = _qa_problem(qa)
+            if reason is None:
+                try:
+                    yield _qa_example(qa, title, context, offset_unit)
+                    continue
+                except ValueError as error:
+                    reason = str(error)
+            _reject(title, qa.get('id') if isinstance(qa, dict) else None,
+                    reason)
+
+
+# This function validates one qa against the schema, returning (pointer,
+# message) pairs for each violation. context is the enclosing paragraph's
+# context string (None if that was itself malformed), used to check that
+# answer spans actually match.
+def _validate_qa(qa, context, pointer):
+    if not isinstance(qa, dict):
+        return [(pointer, 'qa is not an object')]
+    problems = []
+    for key in ('id', 'question'):
+        if not isinstance(qa.get(key), str):
+            problems.append(('{}.{}'.format(pointer, key),
+                             'missing or non-string {!r}'.format(key)))
+    if 'is_impossible' in qa and not isinstance(qa['is_impossible'], bool):
+        problems.append((pointer + '.is_impossible',
+                         'non-boolean is_impossible'))
+    if not isinstance(qa.get('answers'), list):
+        problems.append((pointer + '.answers', 'missing or non-list answers'))
+        return problems
+    for i, answer in enumerate(qa['answers']):
+        apointer = '{}.answers[{}]'.format(pointer, i)
+        if not isinstance(answer, dict):
+            problems.append((apointer, 'answer is not an object'))
+            continue
+        text = answer.get('text')
+        start = answer.get('answer_start')
+        if not isinstance(text, str):
+            problems.append((apointer + '.text',
+                             'missing or non-string text'))
+        if not isinstance(start, int) or isinstance(start, bool):
+            problems.append((apointer + '.answer_start',
+                             'missing or non-integer answer_start'))
+        elif isinstance(text, str) and context is not None \
+                and context[max(start, 0):start + len(text)] != text:
+            problems.append((apointer + '.answer_start',
+                             'answer text does not match context at '
+                             'offset {}'.format(start)))
+    return problems
+
+
+# This function validates a raw SQuAD document against the expected schema
+# and returns a list of (pointer, message) pairs, one per violation, with
+# pointers like "data[12].paragraphs[3].qas[7].answers[0].answer_start" so
+# reports can be acted on without hunting through the file.
+def validate_squad(raw):
+    if not isinstance(raw, dict):
+        return [('', 'document is not an object')]
+    if not isinstance(raw.get('data'), list):
+        return [('data', 'missing or non-list data')]
+
+    problems = []
+    for i, article in enumerate(raw['data']):
+        pointer = 'data[{}]'.format(i)
+        if not isinstance(article, dict):
+            problems.append((pointer, 'article is not an object'))
+            continue
+        if 'title' in article and not isinstance(article['title'], str):
+            problems.append((pointer + '.title', 'non-string title'))
+        if not isinstance(article.get('paragraphs'), list):
+            problems.append((pointer + '.paragraphs',
+                             'missing or non-list paragraphs'))
+            continue
+        for j, paragraph in enumerate(article['paragraphs']):
+            ppointer = '{}.paragraphs[{}]'.format(pointer, j)
+            if not isinstance(paragraph, dict):
+                problems.append((ppointer, 'paragraph is not an object'))
+                continue
+            context = paragraph.get('context')
+            if not isinstance(context, str):
+                problems.append((ppointer + '.context',
+                                 'missing or non-string context'))
+                context = None
+            if not isinstance(paragraph.get('qas'), list):
+                problems.append((ppointer + '.qas', 'missing or non-list qas'))
+                continue
+            for k, qa in enumerate(paragraph['qas']):
+                problems.extend(_validate_qa(
+                    qa, context, '{}.qas[{}]'.format(ppointer, k)))
+    return problems
+
+
+# This generator yields text chunks from a buffered file read.
+def _file_chunks(path, chunk_size):
+    with open(path, encoding='utf-8') as f:
+        while True:
+            chunk = f.read(chunk_size)
+            if not chunk:
+                return
+            yield chunk
+
+
+# This generator yields text chunks from a memory-mapped file. The kernel
+# pages the bytes in on demand and memoryview slicing avoids an intermediate
+# copy, so only the chunk being decoded is ever resident as a Python string —
+# the cheapest way to walk a multi-GB dump. UTF-8 sequences split across a
+# chunk boundary are handled by the incremental decoder.
+def _mmap_chunks(path, chunk_size):
+    with open(path, 'rb') as f:
+        with mmap.mmap(f.fileno(), 0, access=mmap.ACCESS_READ) as mapped:
+            decode = codecs.getincrementaldecoder('utf-8')().decode
+            view = memoryview(mapped)
+            try:
+                for position in range(0, len(mapped), chunk_size):
+                    text = decode(view[position:position + chunk_size])
+                    if text:
+                        yield text
+                tail = decode(b'', True)
+                if tail:
+                    yield tail
+            finally:
+                # The view must be released before the map closes, or closing
+                # raises BufferError.
+                view.release()
+
+
+# This generator yields flattened examples from a SQuAD-format file without
+# materializing the whole document: the "data" array is decoded one article
+# at a time from a bounded read buffer, so memory stays proportional to the
+# largest single title rather than the corpus. With use_mmap the input bytes
+# come from a memory map instead of buffered reads. It assumes the standard
+# {"version": ..., "data": [...]} layout.
+def iter_raw_examples(path, offset_unit='chars', chunk_size=1 << 20,
+                      use_mmap=False):
+    if use_mmap:
+        chunks = _mmap_chunks(path, chunk_size)
+    else:
+        chunks = _file_chunks(path, chunk_size)
+    decoder = json.JSONDecoder()
+
+    buffer = ''
+    while True:
+        marker = buffer.find('"data"')
+        if marker != -1:
+            start = buffer.find('[', marker)
+            if start != -1:
+                buffer = buffer[start + 1:]
+                break
+        chunk = next(chunks, '')
+        if not chunk:
+            raise ValueError('no "data" array found in {}'.format(path))
+        buffer += chunk
+
+    while True:
+        buffer = buffer.lstrip()
+        while not buffer:
+            chunk = next(chunks, '')
+            if not chunk:
+                raise ValueError(
+                    'unterminated "data" array in {}'.format(path))
+            buffer = chunk.lstrip()
+        if buffer[0] == ',':
+            buffer = buffer[1:]
+            continue
+        if buffer[0] == ']':
+            return
+        try:
+            article, end = decoder.raw_decode(buffer)
+        except ValueError:
+            chunk = next(chunks, '')
+            if not chunk:
+                raise
+            buffer += chunk
+            continue
+        buffer = buffer[end:]
+        for example in _article_examples(article, offset_unit):
+            yield example
+
+
+# This generator streams examples from JSONL one line at a time.
+def iter_jsonl_examples(path):
+    with open(path, encoding='utf-8') as f:
+        for line in f:
+            if line.strip():
+                yield json.loads(line)
+
+
+# This function reads examples from JSONL: one flattened example object per
+# line, the same shape as the internal representation. JSONL is qabuild's
+# language-neutral interchange format — non-Python consumers (C++ loaders,
+# jq, external transforms) can stream it line-by-line without loading the
+# whole nested SQuAD structure.
+def read_jsonl_examples(path):
+    examples = collections.OrderedDict()
+    with open(path, encoding='utf-8') as f:
+        for line in f:
+            if line.strip():
+                _insert_example(examples, json.loads(line))
+    # Every line parses its own copy of the context, so interning pays off
+    # most here.
+    return intern_contexts(examples)
+
+
+# This function writes flattened examples as JSONL, one example object per
+# line (see read_jsonl_examples).
+def write_jsonl_examples(examples, path):
+    if isinstance(examples, dict):
+        examples = examples.values()
+    with open(path, encoding='utf-8', mode='w') as f:
+        for example in examples:
+            f.write(json.dumps(example, ensure_ascii=False) + '\n')
+
+
+# This function builds the output qa dict for one example (shared by the
+# nested and streaming writers).
+def _example_qa(example, offset_unit):
+    answers = example['answers']
+    if offset_unit != 'chars':
+        answers = [{'text': a['text'],
+                    'answer_start': from_char_offset(
+                        example['context'], a['answer_start'], offset_unit)}
+                   for a in answers]
+    qa = {
+        'id': example['id'],
+        'question': example['question'],
+        'answers': answers,
+    }
+    if 'is_impossible' in example:
+        qa['is_impossible'] = example['is_impossible']
+    return qa
+
+
+# This function re-nests flattened examples into the SQuAD structure (the
+# dict that json-serializes to a SQuAD file). Examples sharing a (title,
+# context) pair are re-grouped into one paragraph, preserving first-seen
+# order of titles and contexts. Like parse_squad, this is independent of the
+# filesystem.
+def dump_squad(examples, version='1.1', offset_unit='chars'):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    articles = collections.OrderedDict()
+    for example in examples:
+        paragraphs = articles.setdefault(example['title'], collections.OrderedDict())
+        qas = paragraphs.setdefault(example['context'], [])
+        qas.append(_example_qa(example, offset_unit))
+
+    data = []
+    for title, paragraphs in articles.items():
+        data.append({
+            'title': title,
+            'paragraphs': [{'context': context, 'qas': qas}
+                           for context, qas in paragraphs.items()]
+        })
+
+    return {'version': version, 'data': data}
+
+
+# This function writes flattened examples back out as a nested SQuAD file
+# (see dump_squad).
+def write_squad_file(examples, path, version='1.1', offset_unit='chars'):
+    raw = dump_squad(examples, version=version, offset_unit=offset_unit)
+    with open(path, encoding='utf-8', mode='w') as f:
+        json.dump(raw, f, ensure_ascii=False)
+
+
+# This function writes examples in the normalized context-table format: each
+# unique context is stored once in a "contexts" table and examples reference
+# it by index, shrinking paragraph-heavy files severalfold. The format is
+# marked with a "format" key so loaders can tell it apart from SQuAD JSON.
+def write_context_table_file(examples, path, version='1.1'):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    contexts = []
+    context_index = {}
+    rows = []
+    for example in examples:
+        context = example['context']
+        if context not in context_index:
+            context_index[context] = len(contexts)
+            contexts.append(context)
+        row = dict(example)
+        row['context'] = context_index[context]
+        rows.append(row)
+
+    with open(path, encoding='utf-8', mode='w') as f:
+        json.dump({'version': version, 'format': 'qabuild-context-table',
+                   'contexts': contexts, 'examples': rows},
+                  f, ensure_ascii=False)
+
+
+# This function loads a context-table file back into the flattened
+# representation, re-expanding context indices (the shared table entries keep
+# contexts interned for free).
+def read_context_table_file(path):
+    with open(path, encoding='utf-8') as f:
+        raw = json.load(f)
+    if raw.get('format') != 'qabuild-context-table':
+        raise ValueError('{} is not a context-table file'.format(path))
+
+    contexts = raw['contexts']
+    examples = collections.OrderedDict()
+    for row in raw['examples']:
+        example = dict(row)
+        example['context'] = contexts[row['context']]
+        _insert_example(examples, example)
+    return examples
+
+
+# This function writes examples to a SQuAD-format file as it consumes them:
+# contiguous runs of the same title become one article, flushed as soon as
+# the title changes, so memory stays proportional to a single title rather
+# than the corpus. Input order therefore determines grouping; sort or group
+# by title upstream if articles are interleaved. Returns the number of
+# examples written.
+def write_squad_stream(examples, path, version='1.1', offset_unit='chars'):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    written = 0
+    with open(path, encoding='utf-8', mode='w') as f:
+        f.write('{"version": ' + json.dumps(version) + ', "data": [')
+        title = None
+        paragraphs = None
+        first_article = True
+
+        def flush():
+            nonlocal first_article
+            if paragraphs is None:
+                return
+            article = {
+                'title': title,
+                'paragraphs': [{'context': context, 'qas': qas}
+                               for context, qas in paragraphs.items()]
+            }
+            if not first_article:
+                f.write(', ')
+            f.write(json.dumps(article, ensure_ascii=False))
+            first_article = False
+
+        for example in examples:
+            if paragraphs is None or example['title'] != title:
+                flush()
+                title = example['title']
+                paragraphs = collections.OrderedDict()
+            paragraphs.setdefault(example['context'], []).append(
+                _example_qa(example, offset_unit))
+            written += 1
+        flush()
+        f.write(']}')
+    return written
@@ -0,0 +1,2928 @@
+import argparse
+import csv
+import fnmatch
+import http.server
+import json
+import logging
+import math
+import collections
+import glob
+import random
+
+import os
+import resource
+import subprocess
+import sys
+import tempfile
+import time
+import tomllib
+import tracemalloc
+import urllib.parse
+
+import qa_data
+from qa_data import read_raw_examples, write_squad_file
+import augment
+import export
+import importers
+import manifest
+import plugins
+import progress
+import retrieval
+import sampling
+import stats
+import synth
+import transforms
+import tui
+
+# Exit codes, so CI wrappers can tell failure modes apart:
+#   0 success; 1 runtime error (IO, subprocess, ...); 2 bad usage (argparse's
+#   convention); 3 input parse error; 4 validation failure (offset/id/checksum
+#   checks that found bad data).
+EXIT_ERROR = 1
+EXIT_PARSE = 3
+EXIT_VALIDATION = 4
+
+# Structured per-run counts for --json-summary: commands that compute
+# composition details (mixing buckets, curriculum stages, ...) record them
+# here and main() folds them into the emitted summary object.
+run_summary = collections.OrderedDict()
+
+# qabuild is a command-line toolkit for constructing, augmenting, and analyzing
+# SQuAD-format QA datasets. Each subcommand reads one or more SQuAD-format JSON
+# files and writes derived datasets; run `python3 qabuild.py <command> --help`
+# for the arguments of a specific command.
+#
+# qabuild is also usable in-process from other research code, without shelling
+# out and re-reading JSON: the underlying modules (qa_data, synth, augment,
+# transforms, sampling, ...) operate on plain example dicts and can be imported
+# directly, and invoke() below runs any subcommand programmatically.
+
+
+def run_synth(args):
+    if not args.entities and not args.distractor_bank:
+        raise SystemExit('synth: pass --entities and/or --distractor-bank')
+    examples = read_raw_examples(args.infile)
+    entities = synth.load_entity_list(args.entities) if args.entities else None
+    bank = synth.load_distractor_bank(args.distractor_bank) \
+        if args.distractor_bank else None
+    synthesized = synth.synth_distractor_examples(
+        examples, entities, position=args.position,
+        num_distractors=args.num_distractors, bank=bank,
+        rng=random.Random(args.seed))
+    if args.hash_ids:
+        synthesized = qa_data.hash_variant_ids(
+            synthesized, 'synth-{}'.format(args.position), args.seed)
+    write_squad_file(synthesized, args.output)
+    logging.info('Synthesized {} distractor examples from {} inputs -> {}'.format(
+        len(synthesized), len(examples), args.output))
+
+
+def run_augment(args):
+    examples = read_raw_examples(args.infile)
+    rng = random.Random(args.seed)
+    outputs = collections.OrderedDict()
+    if args.gazetteer:
+        gazetteer = synth.load_entity_list(args.gazetteer)
+        outputs.update(augment.gazetteer_perturb_examples(
+            examples, gazetteer, args.variants, rng))
+    if args.perturb_numbers:
+        outputs.update(augment.number_perturb_examples(
+            examples, args.number_delta, args.variants, rng))
+    if args.negate:
+        outputs.update(augment.negation_flip_examples(examples))
+    if args.synonyms:
+        lexicon = augment.load_synonym_lexicon(args.synonyms)
+        outputs.update(augment.synonym_replace_examples(
+            examples, lexicon, args.synonym_rate, rng))
+    if args.paraphrase:
+        rules = augment.PARAPHRASE_RULES
+        if args.paraphrase_rules:
+            rules = augment.load_paraphrase_rules(args.paraphrase_rules)
+        lexicon = None
+        if args.synonyms:
+            lexicon = augment.load_synonym_lexicon(args.synonyms)
+        outputs.update(augment.paraphrase_examples(
+            examples, rules, args.variants, rng, lexicon=lexicon,
+            rate=args.synonym_rate))
+    if args.backtranslate:
+        outputs.update(augment.backtranslate_examples(
+            examples, _backtranslate_questions(examples, args)))
+    if args.case:
+        modes = args.case.split(',')
+        unknown = [m for m in modes if m not in augment.CASE_MODES]
+        if unknown:
+            raise SystemExit('augment: unrecognized casing mode(s): {}'
+                             .format(', '.join(unknown)))
+        outputs.update(augment.case_perturb_examples(
+            examples, modes, rng, target=args.case_target))
+    if args.typo_rate:
+        outputs.update(augment.typo_noise_examples(
+            examples, args.typo_rate, rng, target=args.typo_target))
+    if args.token_dropout:
+        protected = []
+        if args.dropout_protect:
+            with open(args.dropout_protect, encoding='utf-8') as f:
+                protected = [line.strip() for line in f if line.strip()]
+        outputs.update(augment.token_dropout_examples(
+            examples, args.token_dropout, rng, protected=protected))
+    if args.hash_ids:
+        outputs = qa_data.hash_variant_ids(outputs, 'augment', args.seed)
+    write_squad_file(outputs, args.output)
+    logging.info('Generated {} augmented examples from {} inputs -> {}'.format(
+        len(outputs), len(examples), args.output))
+
+
+def run_transplant(args):
+    clean = read_raw_examples(args.infile)
+    adversarial = read_raw_examples(args.adv_file)
+    outputs = augment.transplant_distractor_examples(
+        clean, adversarial, random.Random(args.seed),
+        max_per_example=args.max_per_example, position=args.position)
+    if args.hash_ids:
+        outputs = qa_data.hash_variant_ids(outputs, 'transplant', args.seed)
+    write_squad_file(outputs, args.output)
+    logging.info('Transplanted distractors into {} examples ({} clean, '
+                 '{} adversarial inputs) -> {}'.format(
+                     len(outputs), len(clean), len(adversarial), args.output))
+
+
+def run_anonymize(args):
+    examples = read_raw_examples(args.infile)
+    names = []
+    if args.names:
+        for ents in synth.load_entity_list(args.names).values():
+            names.extend(ents)
+    outputs, mapping = transforms.anonymize_examples(examples, names=names)
+    write_squad_file(outputs, args.output)
+    if args.mapping:
+        with open(args.mapping, encoding='utf-8', mode='w') as f:
+            for value, replacement in mapping.items():
+                f.write('{}\t{}\n'.format(value, replacement))
+    logging.info('Anonymized {} examples ({} distinct values redacted) -> {}'
+                 .format(len(outputs), len(mapping), args.output))
+
+
+def run_contrast(args):
+    examples = read_raw_examples(args.infile)
+    gazetteer = synth.load_entity_list(args.entities)
+    outputs = augment.contrast_swap_examples(
+        examples, gazetteer, args.variants, random.Random(args.seed))
+    if args.hash_ids:
+        outputs = qa_data.hash_variant_ids(outputs, 'contrast', args.seed)
+    write_squad_file(outputs, args.output)
+    logging.info('Built {} contrast examples from {} inputs -> {}'.format(
+        len(outputs), len(examples), args.output))
+
+
+def run_distractors(args):
+    examples = read_raw_examples(args.infile)
+    seen = set()
+    count = 0
+    with open(args.output, encoding='utf-8', mode='w') as f:
+        for example in examples.values():
+            candidate = example['id']
+            while '-' in candidate:
+                candidate = candidate.rsplit('-', 1)[0]
+                if candidate in examples:
+                    break
+            if candidate == example['id'] or candidate not in examples:
+                continue
+            base = examples[candidate]
+            span = transforms.extract_insertion(
+                base['context'], example['context'])
+            if span is None:
+                continue
+            sentence = example['context'][span[0]:span[1]].strip()
+            if not sentence or sentence in seen:
+                continue
+            seen.add(sentence)
+            entry = collections.OrderedDict([
+                ('sentence', sentence),
+                ('title', example['title']),
+                ('source_id', example['id']),
+            ])
+            f.write(json.dumps(entry, ensure_ascii=False) + '\n')
+            count += 1
+    logging.info('Extracted {} distractor sentences from {} examples -> {}'
+                 .format(count, len(examples), args.output))
+
+
+# This function resolves round-tripped paraphrases for every question in
+# `examples`, shelling out to args.backtranslate (one question per stdin
+# line, one paraphrase per stdout line, same order) only for questions
+# missing from the cache file, which is updated afterwards so reruns don't
+# re-translate. Returns a question -> paraphrase map.
+def _backtranslate_questions(examples, args):
+    cache = collections.OrderedDict()
+    if args.backtranslate_cache and os.path.exists(args.backtranslate_cache):
+        with open(args.backtranslate_cache, encoding='utf-8') as f:
+            cache = collections.OrderedDict(json.load(f))
+
+    pending = []
+    for example in examples.values():
+        question = example['question']
+        if question not in cache and question not in pending:
+            pending.append(question)
+    if pending:
+        result = subprocess.run(
+            args.backtranslate, shell=True,
+            input=''.join(question + '\n' for question in pending),
+            capture_output=True, text=True)
+        if result.returncode != 0:
+            raise SystemExit(
+                'augment: back-translation command exited with status {}:\n{}'
+                .format(result.returncode, result.stderr.strip()))
+        lines = result.stdout.splitlines()
+        if len(lines) != len(pending):
+            raise SystemExit(
+                'augment: back-translation returned {} lines for {} '
+                'questions'.format(len(lines), len(pending)))
+        cache.update(zip(pending, (line.strip() for line in lines)))
+        if args.backtranslate_cache:
+            with open(args.backtranslate_cache, encoding='utf-8',
+                      mode='w') as f:
+                json.dump(cache, f, ensure_ascii=False, indent=1)
+        logging.info('Back-translated {} questions ({} served from cache)'
+                     .format(len(pending), len(cache) - len(pending)))
+    else:
+        logging.info('Back-translation fully served from cache ({} entries)'
+                     .format(len(cache)))
+    return cache
+
+
+def run_ablate(args):
+    examples = read_raw_examples(args.infile)
+    rng = random.Random(args.seed)
+    if args.mode == 'shuffle-sentences':
+        outputs = transforms.shuffle_sentences_examples(examples, rng)
+    elif args.mode == 'answer-sentence-only':
+        outputs = transforms.answer_sentence_only_examples(examples)
+    elif args.mode == 'no-answer-sentence':
+        outputs = transforms.drop_answer_sentence_examples(examples)
+    elif args.mode == 'question-only':
+        outputs = transforms.partial_input_examples(examples, keep='question')
+    elif args.mode == 'context-only':
+        outputs = transforms.partial_input_examples(examples, keep='context')
+    else:
+        raise ValueError('Unrecognized ablation mode: {}'.format(args.mode))
+    # Unanswerable outputs follow the SQuAD 2.0 schema.
+    version = 'v2.0' if args.mode in ('no-answer-sentence', 'question-only') else '1.1'
+    write_squad_file(outputs, args.output, version=version)
+    logging.info('Wrote {} examples ({} mode) -> {}'.format(
+        len(outputs), args.mode, args.output))
+
+
+def run_truncate(args):
+    examples = read_raw_examples(args.infile)
+    outputs = transforms.truncate_context_examples(examples, args.max_context_chars)
+    write_squad_file(outputs, args.output)
+    logging.info('Wrote {} examples (from {}) with contexts capped at {} chars -> {}'.format(
+        len(outputs), len(examples), args.max_context_chars, args.output))
+
+
+def run_concat(args):
+    examples = read_raw_examples(args.infile)
+    outputs = transforms.concat_title_examples(examples)
+    write_squad_file(outputs, args.output)
+    logging.info('Wrote {} examples with per-title concatenated contexts -> {}'.format(
+        len(outputs), args.output))
+
+
+def run_chunk(args):
+    examples = read_raw_examples(args.infile)
+    outputs = transforms.chunk_examples(
+        examples, args.max_len, args.stride, keep_empty=not args.drop_empty)
+    write_squad_file(outputs, args.output, version='v2.0')
+    logging.info('Wrote {} chunks from {} examples -> {}'.format(
+        len(outputs), len(examples), args.output))
+
+
+# This function reads and merges one or more SQuAD-format inputs; each
+# argument may be a literal path or a glob pattern (quoted so the shell does
+# not expand it first). When more than one file contributes, every example
+# records which file it came from under 'source', so stats and exports can be
+# traced back without pre-merging JSON by hand.
+def read_input_examples(patterns, offset_unit='chars'):
+    paths = []
+    for pattern in patterns:
+        matched = sorted(glob.glob(pattern))
+        # An unmatched non-glob pattern falls through to open() for the
+        # normal missing-file error.
+        paths.extend(matched if matched else [pattern])
+
+    merged = collections.OrderedDict()
+    for path in paths:
+        for example_id, example in read_raw_examples(
+                path, offset_unit=offset_unit).items():
+            if len(paths) > 1:
+                example = dict(example)
+                example['source'] = path
+            merged[example_id] = example
+    return merged
+
+
+def run_stats(args):
+    examples = read_input_examples(args.infiles)
+    count_tokens = None
+    if args.tokenizer:
+        count_tokens = stats.load_tokenizer(args.tokenizer)
+    result = stats.compute_stats(examples, count_tokens=count_tokens,
+                                 token_limit=args.token_limit)
+    print(json.dumps(result, indent=2))
+
+
+def run_export_hits(args):
+    examples = read_raw_examples(args.infile)
+    selected = examples
+    if args.ids:
+        with open(args.ids, encoding='utf-8') as f:
+            wanted = set(line.strip() for line in f if line.strip())
+        selected = collections.OrderedDict(
+            (example_id, example) for example_id, example in examples.items()
+            if example_id in wanted)
+    stem = os.path.splitext(args.output)[0]
+    paths = export.export_hit_batches(selected, stem, args.batch_size)
+    logging.info('Exported {} examples as {} HIT batch(es) -> {}'.format(
+        len(selected), len(paths), ', '.join(paths)))
+
+
+def run_agreement(args):
+    examples = read_raw_examples(args.infile)
+    steps = tuple(s for s in args.normalize.split(',') if s)
+    for step in steps:
+        if step not in stats.NORMALIZE_STEPS:
+            raise SystemExit('agreement: unknown normalization step {!r} '
+                             '(choose from {})'.format(
+                                 step, ', '.join(stats.NORMALIZE_STEPS)))
+    tokenize = stats.load_token_splitter(args.tokenize)
+    report, per_question = stats.compute_agreement(examples, steps, tokenize)
+    print(json.dumps(report, indent=2))
+    if args.per_question:
+        with open(args.per_question, encoding='utf-8', mode='w') as f:
+            f.write('id\texact_match\tanswer_f1\tspan_overlap\n')
+            for example_id, values in per_question.items():
+                f.write('{}\t{:.4f}\t{:.4f}\t{:.4f}\n'.format(
+                    example_id, values['exact_match'], values['answer_f1'],
+                    values['span_overlap']))
+    logging.info('Agreement over {} of {} questions with 2+ answers'.format(
+        report['num_multi_annotated'], report['num_questions']))
+
+
+# This function tells whether an id is an adversarial/augmented variant:
+# variant ids strip (suffix by suffix) to a base id that is also present.
+def _is_variant_id(example_id, examples):
+    candidate = example_id
+    while '-' in candidate:
+        candidate = candidate.rsplit('-', 1)[0]
+        if candidate in examples:
+            return True
+    return False
+
+
+# This function loads a predictions file for calibration analysis: a JSON
+# object mapping id -> {'text': ..., 'confidence'|'score'|'logit': ...} (or
+# a [text, confidence] pair). Returns an OrderedDict id -> (text,
+# confidence). Logit-scale inputs (anything outside [0, 1]) are squashed
+# through a sigmoid so binning works on probabilities.
+def _load_predictions(path):
+    with open(path, encoding='utf-8') as f:
+        raw = json.load(f)
+
+    predictions = collections.OrderedDict()
+    for example_id, value in raw.items():
+        if isinstance(value, dict):
+            text = value.get('text', '')
+            confidence = value.get(
+                'confidence', value.get('score', value.get('logit')))
+        elif isinstance(value, list) and len(value) >= 2:
+            text, confidence = value[0], value[1]
+        else:
+            raise ValueError(
+                'prediction for {!r} must be an object with text and '
+                'confidence (or a [text, confidence] pair)'.format(example_id))
+        if confidence is None:
+            raise ValueError(
+                'prediction for {!r} has no confidence/score/logit'.format(
+                    example_id))
+        predictions[example_id] = (text, float(confidence))
+
+    if any(confidence < 0 or confidence > 1
+           for _, confidence in predictions.values()):
+        predictions = collections.OrderedDict(
+            (example_id, (text, 1 / (1 + math.exp(-confidence))))
+            for example_id, (text, confidence) in predictions.items())
+    return predictions
+
+
+def run_calibration(args):
+    examples = read_raw_examples(args.infile)
+    predictions = _load_predictions(args.predictions)
+
+    subsets = collections.OrderedDict(
+        [('overall', []), ('clean', []), ('adversarial', [])])
+    for example_id, (text, confidence) in predictions.items():
+        example = examples.get(example_id)
+        if example is None:
+            continue
+        if example.get('is_impossible'):
+            correct = not text.strip()
+        else:
+            normalized = stats.normalize_answer(text)
+            correct = any(stats.normalize_answer(a['text']) == normalized
+                          for a in example['answers'])
+        subsets['overall'].append((confidence, correct))
+        key = ('adversarial' if _is_variant_id(example_id, examples)
+               else 'clean')
+        subsets[key].append((confidence, correct))
+
+    report = collections.OrderedDict(
+        (name, stats.compute_calibration(records, args.bins))
+        for name, records in subsets.items() if records)
+    print(json.dumps(report, indent=2))
+    logging.info('Calibration over {} predictions: ECE {:.4f}'.format(
+        report['overall']['count'], report['overall']['ece']))
+
+
+# This function loads a sidecar metadata file mapping ids to categorical
+# fields, for metric breakdowns: either a JSON map id -> {field: value} or a
+# TSV whose header row starts with an id column followed by field names.
+def _load_metadata(path):
+    with open(path, encoding='utf-8') as f:
+        text = f.read()
+    if text.lstrip().startswith('{'):
+        raw = json.loads(text)
+        return collections.OrderedDict(
+            (example_id, collections.OrderedDict(fields.items()))
+            for example_id, fields in raw.items())
+
+    rows = [line.split('\t') for line in text.splitlines() if line.strip()]
+    fields = rows[0][1:]
+    metadata = collections.OrderedDict()
+    for row in rows[1:]:
+        metadata[row[0]] = collections.OrderedDict(zip(fields, row[1:]))
+    return metadata
+
+
+def run_score(args):
+    examples = read_raw_examples(args.infile)
+    with open(args.predictions, encoding='utf-8') as f:
+        predictions = json.load(f)
+    metadata = _load_metadata(args.metadata) if args.metadata else {}
+
+    groups = collections.OrderedDict()
+
+    def tally(group, em, f1):
+        bucket = groups.setdefault(group, {'count': 0, 'em': 0.0, 'f1': 0.0})
+        bucket['count'] += 1
+        bucket['em'] += em
+        bucket['f1'] += f1
+
+    scored = 0
+    for example_id, example in examples.items():
+        if example_id not in predictions:
+            continue
+        value = predictions[example_id]
+        text = value['text'] if isinstance(value, dict) else value
+        if example.get('is_impossible'):
+            em = f1 = float(not text.strip())
+        else:
+            normalized = stats.normalize_answer(text)
+            em = float(any(stats.normalize_answer(a['text']) == normalized
+                           for a in example['answers']))
+            f1 = max((stats.answer_f1(text, a['text'])
+                      for a in example['answers']), default=0.0)
+        scored += 1
+        tally('overall', em, f1)
+        tally('subset:adversarial' if _is_variant_id(example_id, examples)
+              else 'subset:clean', em, f1)
+        tally('title:{}'.format(example['title']), em, f1)
+        for field, category in metadata.get(example_id, {}).items():
+            tally('{}:{}'.format(field, category), em, f1)
+    if not scored:
+        raise SystemExit('score: no ids matched between the dataset and '
+                         'the predictions')
+
+    print('{:<40}{:>8}{:>10}{:>10}'.format('group', 'count', 'EM', 'F1'))
+    for group, bucket in groups.items():
+        print('{:<40}{:>8}{:>10.2f}{:>10.2f}'.format(
+            group, bucket['count'],
+            100.0 * bucket['em'] / bucket['count'],
+            100.0 * bucket['f1'] / bucket['count']))
+    if args.csv:
+        with open(args.csv, encoding='utf-8', mode='w', newline='') as f:
+            writer = csv.writer(f)
+            writer.writerow(['group', 'count', 'em', 'f1'])
+            for group, bucket in groups.items():
+                writer.writerow([group, bucket['count'],
+                                 bucket['em'] / bucket['count'],
+                                 bucket['f1'] / bucket['count']])
+    logging.info('Scored {} of {} examples in {} group(s)'.format(
+        scored, len(examples), len(groups)))
+
+
+def run_contamination(args):
+    train = read_raw_examples(args.infile)
+    held_out = read_raw_examples(args.eval_file)
+    contaminated = stats.find_contamination(train, held_out, n=args.ngram)
+    for train_id, eval_id in contaminated.items():
+        print('{}\t{}'.format(train_id, eval_id))
+    if contaminated:
+        logging.error('contamination: {} of {} training examples share a '
+                      '{}-gram with the eval set'.format(
+                          len(contaminated), len(train), args.ngram))
+        sys.exit(EXIT_VALIDATION)
+    logging.info('No {}-gram overlap between {} training and {} eval '
+                 'examples'.format(args.ngram, len(train), len(held_out)))
+
+
+def run_delta(args):
+    examples = read_raw_examples(args.infile)
+    with open(args.predictions, encoding='utf-8') as f:
+        predictions = json.load(f)
+
+    def f1_for(example_id):
+        value = predictions.get(example_id)
+        if value is None:
+            return None
+        text = value['text'] if isinstance(value, dict) else value
+        example = examples[example_id]
+        if example.get('is_impossible'):
+            return float(not text.strip())
+        return max((stats.answer_f1(text, a['text'])
+                    for a in example['answers']), default=0.0)
+
+    pairs = []
+    for example_id in examples:
+        candidate = example_id
+        base = None
+        while '-' in candidate:
+            candidate = candidate.rsplit('-', 1)[0]
+            if candidate in examples:
+                base = candidate
+                break
+        if base is None:
+            continue
+        base_f1 = f1_for(base)
+        variant_f1 = f1_for(example_id)
+        if base_f1 is None or variant_f1 is None:
+            continue
+        pairs.append((base, example_id, base_f1, variant_f1))
+    if not pairs:
+        raise SystemExit('delta: no base/variant pairs with predictions '
+                         'on both sides')
+
+    mean_base = sum(p[2] for p in pairs) / len(pairs)
+    mean_variant = sum(p[3] for p in pairs) / len(pairs)
+    regressions = sum(1 for p in pairs if p[2] > p[3])
+    print('pairs: {}'.format(len(pairs)))
+    print('mean clean F1: {:.2f}  mean adversarial F1: {:.2f}  '
+          'mean delta: {:.2f}'.format(
+              100.0 * mean_base, 100.0 * mean_variant,
+              100.0 * (mean_base - mean_variant)))
+    print('regressions (clean > adversarial): {}'.format(regressions))
+    print()
+    print('{:<16}{:<26}{:>8}{:>8}{:>8}'.format(
+        'base', 'variant', 'clean', 'adv', 'delta'))
+    worst = sorted(pairs, key=lambda p: p[3] - p[2])[:args.top]
+    for base, variant_id, base_f1, variant_f1, in worst:
+        print('{:<16}{:<26}{:>8.2f}{:>8.2f}{:>8.2f}'.format(
+            base, variant_id, 100.0 * base_f1, 100.0 * variant_f1,
+            100.0 * (base_f1 - variant_f1)))
+        variant = examples[variant_id]
+        print('    Q: {}'.format(variant['question']))
+        print('    {}'.format(variant['context']))
+        print()
+    logging.info('Delta report over {} pairs; {} regressed'.format(
+        len(pairs), regressions))
+
+
+def run_nbest(args):
+    examples = read_raw_examples(args.infile)
+    with open(args.nbest, encoding='utf-8') as f:
+        nbest = json.load(f)
+
+    ranks = []
+    best_f1s = []
+    for example_id, example in examples.items():
+        candidates = nbest.get(example_id)
+        if not candidates or example.get('is_impossible'):
+            continue
+        gold = set(stats.normalize_answer(a['text'])
+                   for a in example['answers'])
+        rank = None
+        best_f1 = 0.0
+        for position, candidate in enumerate(candidates[:args.top_k], 1):
+            text = candidate.get('text', '')
+            for answer in example['answers']:
+                best_f1 = max(best_f1,
+                              stats.answer_f1(text, answer['text']))
+            if rank is None and stats.normalize_answer(text) in gold:
+                rank = position
+        ranks.append(rank)
+        best_f1s.append(best_f1)
+    if not ranks:
+        raise SystemExit('nbest: no answerable questions matched between '
+                         'the dataset and the predictions')
+
+    report = collections.OrderedDict()
+    report['num_questions'] = len(ranks)
+    # A high oracle@k with a low oracle@1 means the right span is proposed
+    # but misranked; a low oracle@k means span selection itself fails.
+    report['oracle'] = collections.OrderedDict(
+        ('top_{}'.format(k),
+         sum(1 for r in ranks if r is not None and r <= k) / len(ranks))
+        for k in sorted(set([1, 3, 5, args.top_k])) if k <= args.top_k)
+    report['mrr'] = sum(1 / r for r in ranks if r is not None) / len(ranks)
+    report['oracle_f1'] = sum(best_f1s) / len(best_f1s)
+    rank_counts = collections.Counter(
+        str(r) if r is not None else 'none' for r in ranks)
+    report['rank_counts'] = collections.OrderedDict(
+        sorted(rank_counts.items(),
+               key=lambda item: (item[0] == 'none', int(item[0])
+                                 if item[0] != 'none' else 0)))
+    print(json.dumps(report, indent=2))
+    logging.info('N-best over {} questions: oracle@{} {:.3f}, MRR '
+                 '{:.3f}'.format(len(ranks), args.top_k,
+                                 report['oracle']['top_{}'.format(
+                                     args.top_k)], report['mrr']))
+
+
+def run_export_features(args):
+    examples = read_input_examples(args.infiles)
+    num_features = export.export_training_features(
+        examples, args.tokenizer, args.output)
+    logging.info('Exported {} features from {} examples -> {}'.format(
+        num_features, len(examples), args.output))
+
+
+def run_index(args):
+    examples = read_input_examples(args.infiles)
+    index = retrieval.build_index(examples)
+    retrieval.save_index(index, args.output)
+    logging.info('Indexed {} paragraphs from {} examples -> {}'.format(
+        len(index['paragraphs']), len(examples), args.output))
+
+
+def run_search(args):
+    index = retrieval.load_index(args.index)
+    results = retrieval.search(index, args.query, k=args.top_k)
+    for doc_index, score in results:
+        paragraph = index['paragraphs'][doc_index]
+        print('{:.3f}\t{}\t{}'.format(
+            score, paragraph['title'], paragraph['context'][:120]))
+
+
+def run_mine_negatives(args):
+    examples = read_raw_examples(args.infile)
+    index = retrieval.load_index(args.index)
+    negatives = retrieval.mine_hard_negatives(examples, index, k=args.top_k)
+
+    if args.mode == 'attach':
+        # JSONL records pairing each question with its mined negatives, the
+        # format DPR-style retriever training consumes.
+        with open(args.output, encoding='utf-8', mode='w') as f:
+            for example in examples.values():
+                record = {
+                    'id': example['id'],
+                    'question': example['question'],
+                    'answers': [a['text'] for a in example['answers']],
+                    'positive_ctx': {'title': example['title'],
+                                     'context': example['context']},
+                    'hard_negatives': negatives[example['id']],
+                }
+                f.write(json.dumps(record, ensure_ascii=False))
+                f.write('\n')
+    else:  # append
+        outputs = collections.OrderedDict()
+        for example in examples.values():
+            new_example = dict(example)
+            new_example['answers'] = [dict(a) for a in example['answers']]
+            for negative in negatives[example['id']]:
+                # Appending after the original context keeps offsets valid.
+                new_example['context'] = (new_example['context'].rstrip()
+                                          + ' ' + negative['context'])
+            outputs[new_example['id']] = new_example
+        write_squad_file(outputs, args.output)
+    mined = sum(1 for v in negatives.values() if v)
+    logging.info('Mined negatives for {}/{} questions -> {}'.format(
+        mined, len(examples), args.output))
+
+
+def run_retrieval_set(args):
+    examples = read_raw_examples(args.infile)
+    index = retrieval.load_index(args.index)
+    rng = random.Random(args.seed)
+
+    with open(args.output, encoding='utf-8', mode='w') as f:
+        for example in examples.values():
+            passages = [{'title': example['title'], 'context': example['context'],
+                         'is_gold': True}]
+            for doc_index, score in retrieval.search(
+                    index, example['question'], k=args.top_k + 1):
+                paragraph = index['paragraphs'][doc_index]
+                if paragraph['context'] == example['context']:
+                    continue
+                passages.append({'title': paragraph['title'],
+                                 'context': paragraph['context'],
+                                 'is_gold': False})
+                if len(passages) == args.top_k + 1:
+                    break
+            rng.shuffle(passages)
+            gold_index = next(i for i, p in enumerate(passages) if p['is_gold'])
+            record = {
+                'id': example['id'],
+                'question': example['question'],
+                'answers': [a['text'] for a in example['answers']],
+                'passages': [{'title': p['title'], 'context': p['context']}
+                             for p in passages],
+                'gold_index': gold_index,
+            }
+            f.write(json.dumps(record, ensure_ascii=False))
+            f.write('\n')
+    logging.info('Wrote {} multi-passage records -> {}'.format(len(examples), args.output))
+
+
+def run_export_fid(args):
+    examples = read_raw_examples(args.infile)
+    index = retrieval.load_index(args.index)
+
+    records = []
+    for example in examples.values():
+        ctxs = [{'title': example['title'], 'text': example['context'],
+                 'score': 1000.0}]
+        for doc_index, score in retrieval.search(
+                index, example['question'], k=args.top_k + 1):
+            paragraph = index['paragraphs'][doc_index]
+            if paragraph['context'] == example['context']:
+                continue
+            ctxs.append({'title': paragraph['title'],
+                         'text': paragraph['context'],
+                         'score': score})
+            if len(ctxs) == args.top_k + 1:
+                break
+        records.append({
+            'id': example['id'],
+            'question': example['question'],
+            'answers': [a['text'] for a in example['answers']],
+            'ctxs': ctxs,
+        })
+    with open(args.output, encoding='utf-8', mode='w') as f:
+        json.dump(records, f, ensure_ascii=False)
+    logging.info('Wrote {} FiD records -> {}'.format(len(records), args.output))
+
+
+def run_swap_negatives(args):
+    examples = read_raw_examples(args.infile)
+    rng = random.Random(args.seed)
+    outputs = transforms.context_swap_negatives(examples, rng)
+    write_squad_file(outputs, args.output, version='v2.0')
+    logging.info('Generated {} unanswerable negatives from {} examples -> {}'.format(
+        len(outputs), len(examples), args.output))
+
+
+def run_convert(args):
+    examples = read_raw_examples(args.infile, offset_unit=args.offset_unit_in)
+    outputs = collections.OrderedDict()
+    if args.to == 'v2.0':
+        # Upgrade: every example gets an explicit is_impossible flag.
+        for example in examples.values():
+            new_example = dict(example)
+            new_example.setdefault('is_impossible', not example['answers'])
+            outputs[new_example['id']] = new_example
+    else:
+        # Downgrade: the v1.1 schema has no is_impossible field; unanswerable
+        # examples are dropped unless --keep-impossible is passed.
+        for example in examples.values():
+            if example.get('is_impossible') and not args.keep_impossible:
+                continue
+            new_example = dict(example)
+            new_example.pop('is_impossible', None)
+            outputs[new_example['id']] = new_example
+    write_squad_file(outputs, args.output,
+                     version=args.to if args.to == 'v2.0' else '1.1',
+                     offset_unit=args.offset_unit_out)
+    logging.info('Converted {} -> {} examples ({}) -> {}'.format(
+        len(examples), len(outputs), args.to, args.output))
+
+
+def run_import_nq(args):
+    examples = importers.import_nq_jsonl(args.infile)
+    write_squad_file(examples, args.output, version='v2.0')
+    logging.info('Imported {} NQ examples -> {}'.format(len(examples), args.output))
+
+
+def run_import_triviaqa(args):
+    examples = importers.import_triviaqa(args.infile, args.evidence_dir)
+    write_squad_file(examples, args.output)
+    logging.info('Imported {} TriviaQA examples -> {}'.format(len(examples), args.output))
+
+
+def run_import_newsqa(args):
+    examples = importers.import_newsqa(args.infile,
+                                       validated_only=args.validated_only)
+    write_squad_file(examples, args.output, version='v2.0')
+    logging.info('Imported {} NewsQA examples -> {}'.format(len(examples), args.output))
+
+
+def run_normalize(args):
+    if not args.form and not args.whitespace:
+        raise SystemExit('normalize: pass --form and/or --whitespace')
+    outputs = read_raw_examples(args.infile)
+    applied = []
+    if args.form:
+        outputs = transforms.normalize_unicode_examples(outputs, form=args.form)
+        applied.append(args.form)
+    if args.whitespace:
+        outputs = transforms.collapse_whitespace_examples(outputs)
+        applied.append('whitespace')
+    write_squad_file(outputs, args.output)
+    logging.info('Normalized {} examples ({}) -> {}'.format(
+        len(outputs), '+'.join(applied), args.output))
+
+
+def run_filter(args):
+    bounds = (args.min_answer_chars, args.max_answer_chars,
+              args.min_answer_tokens, args.max_answer_tokens)
+    if all(bound is None for bound in bounds):
+        raise SystemExit('filter: pass at least one answer-length bound')
+    examples = read_raw_examples(args.infile)
+    outputs, num_answers, num_examples = \
+        transforms.filter_answer_length_examples(
+            examples, min_chars=args.min_answer_chars,
+            max_chars=args.max_answer_chars,
+            min_tokens=args.min_answer_tokens,
+            max_tokens=args.max_answer_tokens)
+    write_squad_file(outputs, args.output)
+    logging.info('Filtered {} answers ({} examples dropped outright), '
+                 '{} of {} examples kept -> {}'.format(
+                     num_answers, num_examples, len(outputs), len(examples),
+                     args.output))
+
+
+def run_curriculum(args):
+    clean = read_raw_examples(args.clean)
+    adversarial = read_raw_examples(args.adversarial)
+    fractions = [float(f) for f in args.fractions.split(',')]
+    probabilities = fractions
+    if args.target:
+        probabilities = []
+        for target in fractions:
+            probability, num_eligible, max_attainable = \
+                sampling.derive_fraction(clean, adversarial, target)
+            if target > max_attainable:
+                logging.warning(
+                    'curriculum: target {:.0%} exceeds the attainable {:.0%} '
+                    '(only {} of {} clean examples have variants)'.format(
+                        target, max_attainable, num_eligible, len(clean)))
+            probabilities.append(probability)
+    os.makedirs(args.output_dir, exist_ok=True)
+
+    stage_manifest = {'seed': args.seed, 'clean': args.clean,
+                      'adversarial': args.adversarial, 'stages': []}
+    for fraction, (_, mixed, num_adversarial) in zip(
+            fractions, sampling.curriculum_series(
+                clean, adversarial, probabilities, args.seed)):
+        filename = 'curriculum-{:03d}.json'.format(int(round(fraction * 100)))
+        path = os.path.join(args.output_dir, filename)
+        write_squad_file(mixed, path)
+        stage_manifest['stages'].append({
+            'fraction': fraction,
+            'file': filename,
+            'num_total': len(mixed),
+            'num_adversarial': num_adversarial,
+        })
+        logging.info('Stage {:.0%}: {} adversarial / {} total -> {}'.format(
+            fraction, num_adversarial, len(mixed), path))
+    run_summary['stages'] = stage_manifest['stages']
+    with open(os.path.join(args.output_dir, 'curriculum-manifest.json'),
+              encoding='utf-8', mode='w') as f:
+        json.dump(stage_manifest, f, indent=2)
+
+
+def run_mix(args):
+    clean = read_raw_examples(args.clean)
+    adversarial = read_raw_examples(args.adversarial)
+
+    if args.union:
+        mixed, num_adversarial = sampling.get_union_examples(clean, adversarial)
+        write_squad_file(mixed, args.output)
+        logging.info('Union: {} adversarial + {} clean -> {}'.format(
+            num_adversarial, len(clean), args.output))
+        return
+    if args.fraction is None and args.target_fraction is None:
+        raise SystemExit('mix: pass --fraction or --target-fraction '
+                         '(or --union)')
+    if args.fraction is not None and args.target_fraction is not None:
+        raise SystemExit('mix: --fraction and --target-fraction are '
+                         'mutually exclusive')
+    fraction = args.fraction
+    if args.target_fraction is not None:
+        fraction, num_eligible, max_attainable = sampling.derive_fraction(
+            clean, adversarial, args.target_fraction)
+        if args.target_fraction > max_attainable:
+            logging.warning(
+                'mix: target {:.0%} exceeds the attainable {:.0%} (only {} '
+                'of {} clean examples have variants)'.format(
+                    args.target_fraction, max_attainable, num_eligible,
+                    len(clean)))
+        logging.info('mix: replacing eligible examples with probability '
+                     '{:.3f} for a {:.0%} target ({} eligible of {})'.format(
+                         fraction, args.target_fraction, num_eligible,
+                         len(clean)))
+    title_fractions = None
+    if args.title_fractions:
+        title_fractions = sampling.load_title_fractions(args.title_fractions)
+
+    for epoch in range(args.epochs):
+        # Each epoch gets its own RNG stream derived from the seed, so every
+        # training epoch sees a fresh clean/adversarial assignment (as in the
+        # original AddSent training recipe).
+        rng = random.Random('{}-epoch{}'.format(args.seed, epoch))
+        mixed, mapping = sampling.get_append_examples(
+            clean, adversarial, fraction, rng,
+            keep_variant_ids=args.keep_variant_ids,
+            title_fractions=title_fractions)
+        if args.epochs == 1:
+            path = args.output
+        elif args.name_template and '{epoch}' in args.name_template:
+            path = render_output_name(args, args.output, epoch=epoch)
+        else:
+            stem, ext = os.path.splitext(args.output)
+            path = '{}-epoch{}{}'.format(stem, epoch, ext)
+        write_squad_file(mixed, path)
+        run_summary.setdefault('epochs', []).append(collections.OrderedDict([
+            ('path', path),
+            ('adversarial', len(mapping)),
+            ('clean', len(mixed) - len(mapping)),
+        ]))
+        # Record which base ids were replaced by which variants, so the mix is
+        # traceable whether or not variant ids were collapsed.
+        with open(os.path.splitext(path)[0] + '-variants.tsv',
+                  encoding='utf-8', mode='w') as f:
+            for base, variant_id in mapping.items():
+                f.write('{}\t{}\n'.format(base, variant_id))
+        logging.info('Mixed {} adversarial / {} total -> {}'.format(
+            len(mapping), len(mixed), path))
+
+
+def run_sample(args):
+    examples = read_raw_examples(args.infile)
+    rng = random.Random(args.seed)
+    if args.per_type is not None:
+        sampled, counts = sampling.type_balanced_sample(
+            examples, args.per_type, rng)
+        run_summary['question_types'] = counts
+        write_squad_file(sampled, args.output)
+        logging.info('Sampled {} of {} examples ({} question types) -> {}'
+                     .format(len(sampled), len(examples), len(counts),
+                             args.output))
+        return
+    if args.num is None:
+        raise SystemExit('sample: pass --num or --per-type')
+    if args.length_buckets:
+        edges = [int(edge) for edge in args.length_buckets.split(',')]
+        sampled, populations = sampling.length_balanced_sample(
+            examples, edges, args.num, rng,
+            uniform=args.length_mode == 'uniform')
+        run_summary['length_buckets'] = populations
+        write_squad_file(sampled, args.output)
+        logging.info('Sampled {} of {} examples ({} mode over {} length '
+                     'buckets) -> {}'.format(
+                         len(sampled), len(examples), args.length_mode,
+                         len(populations), args.output))
+        return
+    weights = sampling.load_weights(args.weights) if args.weights else {}
+    sampled = sampling.weighted_sample(examples, weights, args.num, rng)
+    write_squad_file(sampled, args.output)
+    logging.info('Sampled {} of {} examples -> {}'.format(
+        len(sampled), len(examples), args.output))
+
+
+def run_stratify(args):
+    examples = read_raw_examples(args.infile)
+    scores = sampling.read_idfile(args.scores)
+    edges = [float(e) for e in args.buckets.split(',')]
+    rng = random.Random(args.seed)
+    sampled, counts = sampling.stratified_sample(
+        examples, scores, edges, args.per_bucket, rng)
+    write_squad_file(sampled, args.output)
+    logging.info('Sampled {} examples from buckets of size {} -> {}'.format(
+        len(sampled), counts, args.output))
+
+
+def run_mine(args):
+    examples = read_raw_examples(args.infile)
+    scores = sampling.read_idfile(args.idfile)
+    clean_scores = None
+    if args.clean_scores:
+        clean_scores = sampling.read_idfile(args.clean_scores)
+
+    mined = collections.OrderedDict()
+    for example_id, example in examples.items():
+        if example_id not in scores or scores[example_id] >= args.f1_below:
+            continue
+        if clean_scores is not None:
+            # "Model-fooled" mode: also require the model to have been correct
+            # on the clean version. Variant ids are matched back to their base
+            # id by stripping suffix segments, as in sampling.match_variants.
+            base_id = example_id
+            while base_id not in clean_scores and '-' in base_id:
+                base_id = base_id.rsplit('-', 1)[0]
+            if (base_id not in clean_scores
+                    or clean_scores[base_id] < args.f1_below):
+                continue
+        mined[example_id] = example
+    write_squad_file(mined, args.output)
+    logging.info('Mined {} of {} examples with F1 < {} -> {}'.format(
+        len(mined), len(examples), args.f1_below, args.output))
+
+
+def run_ensemble(args):
+    score_maps = [sampling.read_idfile(path) for path in args.idfiles]
+    consensus = sampling.ensemble_scores(
+        score_maps, mode=args.mode, threshold=args.threshold)
+    with open(args.output, encoding='utf-8', mode='w') as f:
+        for example_id, score in consensus.items():
+            f.write('{}\t{}\n'.format(example_id, score))
+    logging.info('Ensembled {} runs into {} consensus scores ({}) -> {}'.format(
+        len(score_maps), len(consensus), args.mode, args.output))
+
+
+def run_setop(args):
+    a = read_raw_examples(args.file_a)
+    b = read_raw_examples(args.file_b)
+
+    # Set algebra on example ids; A's copy wins on overlap so the left operand
+    # determines contexts/answers for shared ids, and A's order is preserved.
+    if args.op == 'union':
+        result = collections.OrderedDict(a)
+        for example_id, example in b.items():
+            result.setdefault(example_id, example)
+    elif args.op == 'intersect':
+        result = collections.OrderedDict(
+            (example_id, example) for example_id, example in a.items()
+            if example_id in b)
+    else:
+        result = collections.OrderedDict(
+            (example_id, example) for example_id, example in a.items()
+            if example_id not in b)
+    write_squad_file(result, args.output)
+    logging.info('{}: |A|={} |B|={} -> {} examples -> {}'.format(
+        args.op, len(a), len(b), len(result), args.output))
+
+
+def run_remap_ids(args):
+    examples = read_raw_examples(args.infile)
+
+    mapping_in = {}
+    if args.mapping:
+        with open(args.mapping, encoding='utf-8') as f:
+            for line in f:
+                line = line.rstrip('\n')
+                if line and '\t' in line:
+                    old, new = line.rsplit('\t', 1)
+                    mapping_in[old] = new
+
+    remapped = collections.OrderedDict()
+    mapping_out = collections.OrderedDict()
+    for example_id, example in examples.items():
+        if args.mapping:
+            new_id = mapping_in.get(example_id, example_id)
+        else:
+            new_id = args.template.format(id=example_id)
+        if new_id in remapped:
+            raise SystemExit(
+                'remap-ids: id collision on {!r}'.format(new_id))
+        example = dict(example)
+        example['id'] = new_id
+        remapped[new_id] = example
+        mapping_out[example_id] = new_id
+    write_squad_file(remapped, args.output)
+    # Always write the applied old->new mapping next to the output so merges
+    # stay reversible.
+    with open(os.path.splitext(args.output)[0] + '-idmap.tsv',
+              encoding='utf-8', mode='w') as f:
+        for old, new in mapping_out.items():
+            f.write('{}\t{}\n'.format(old, new))
+    logging.info('Remapped {} ids -> {}'.format(len(remapped), args.output))
+
+
+def run_verify(args):
+    results = manifest.verify_manifest(args.manifest_file)
+    drifted = [(path, status) for path, status in results if status != 'ok']
+    for path, status in results:
+        print('{}\t{}'.format(status, path))
+    if drifted:
+        logging.error('verify: {} of {} files drifted'.format(
+            len(drifted), len(results)))
+        sys.exit(EXIT_VALIDATION)
+    print('verify: all {} files match'.format(len(results)))
+
+
+def run_repro(args):
+    with open(args.manifest_file, encoding='utf-8') as f:
+        record = json.load(f)
+
+    temp_dir = tempfile.mkdtemp(prefix='qabuild-repro-')
+    argv = manifest.redirect_argv_outputs(record, temp_dir)
+    script = os.path.abspath(__file__)
+    logging.info('Rebuilding into {}: {}'.format(temp_dir, ' '.join(argv)))
+    result = subprocess.run([sys.executable, script] + argv)
+    if result.returncode != 0:
+        raise SystemExit('repro: rebuild exited with status {}'.format(
+            result.returncode))
+
+    results = manifest.compare_rebuild(record, temp_dir)
+    drifted = [(path, status) for path, status in results if status != 'ok']
+    for path, status in results:
+        print('{}\t{}'.format(status, path))
+    if drifted:
+        logging.error('repro: {} of {} outputs not reproduced'.format(
+            len(drifted), len(results)))
+        sys.exit(EXIT_VALIDATION)
+    print('repro: all {} outputs reproduced byte-identically'.format(
+        len(results)))
+
+
+def run_pipeline(args):
+    with open(args.config, 'rb') as f:
+        config = tomllib.load(f)
+
+    argp, subparsers = build_parser()
+    for index, step in enumerate(config.get('step', [])):
+        step = dict(step)
+        command = step.pop('command', None)
+        if command is None or command not in subparsers.choices:
+            raise SystemExit(
+                'run: step {} has no valid "command" key'.format(index + 1))
+
+        # Rebuild an argv for the step so the normal subcommand parsing
+        # (defaults, choices, type conversion) applies: positional argument
+        # names become bare values, everything else becomes "--key value"
+        # (underscores map to dashes, booleans to presence/absence).
+        sub_parser = subparsers.choices[command]
+        argv = [command]
+        for action in sub_parser._get_positional_actions():
+            if action.dest not in step:
+                raise SystemExit('run: step {} ({}) is missing {!r}'.format(
+                    index + 1, command, action.dest))
+            value = step.pop(action.dest)
+            values = value if isinstance(value, list) else [value]
+            argv.extend(str(v) for v in values)
+        for key, value in step.items():
+            flag = '--' + key.replace('_', '-')
+            if value is True:
+                argv.append(flag)
+            elif value is False:
+                continue
+            elif isinstance(value, list):
+                argv.append(flag)
+                argv.extend(str(v) for v in value)
+            else:
+                argv.extend([flag, str(value)])
+
+        logging.info('[step {}] {}'.format(index + 1, ' '.join(argv)))
+        step_args = argp.parse_args(argv)
+        step_args.func(step_args)
+        manifest.chain_provenance(step_args)
+
+
+def run_exec(args):
+    examples = read_raw_examples(args.infile)
+    payload = ''.join(json.dumps(example, ensure_ascii=False) + '\n'
+                      for example in examples.values())
+    result = subprocess.run(args.cmd, shell=True, input=payload,
+                            capture_output=True, text=True)
+    if result.returncode != 0:
+        raise SystemExit('exec: command exited with status {}:\n{}'.format(
+            result.returncode, result.stderr.strip()))
+
+    outputs = collections.OrderedDict()
+    for lineno, line in enumerate(result.stdout.splitlines(), 1):
+        if not line.strip():
+            continue
+        example = json.loads(line)
+        for key in ('id', 'title', 'context', 'question', 'answers'):
+            if key not in example:
+                logging.error(
+                    'exec: output line {} is missing {!r}'.format(lineno, key))
+                sys.exit(EXIT_VALIDATION)
+        # External transforms are the most likely place for spans to rot, so
+        # returned offsets are always verified before anything is written.
+        for answer in example['answers']:
+            start = answer['answer_start']
+            if (example['context'][start:start + len(answer['text'])]
+                    != answer['text']):
+                logging.error(
+                    'exec: output line {} ({}): answer {!r} does not match '
+                    'context at offset {}'.format(
+                        lineno, example['id'], answer['text'], start))
+                sys.exit(EXIT_VALIDATION)
+        outputs[example['id']] = example
+    write_squad_file(outputs, args.output)
+    logging.info('Transformed {} -> {} examples via {!r} -> {}'.format(
+        len(examples), len(outputs), args.cmd, args.output))
+
+
+def run_plugin(args):
+    available = plugins.discover_plugins(args.plugins_dir)
+    if args.name not in available:
+        raise SystemExit('plugin: {!r} not found in {} (available: {})'.format(
+            args.name, args.plugins_dir, ', '.join(available) or 'none'))
+    transform = plugins.load_plugin(available[args.name])
+    examples = read_raw_examples(args.infile)
+    try:
+        outputs = plugins.apply_plugin(examples, transform)
+    except ValueError as error:
+        logging.error('plugin: {}'.format(error))
+        sys.exit(EXIT_VALIDATION)
+    write_squad_file(outputs, args.output)
+    logging.info('Transformed {} -> {} examples via plugin {!r} -> {}'.format(
+        len(examples), len(outputs), args.name, args.output))
+
+
+def run_bench(args):
+    with open(args.infile, encoding='utf-8') as f:
+        text = f.read()
+    in_mb = len(text.encode('utf-8')) / (1 << 20)
+    examples = qa_data.parse_squad(text)
+    serialized = json.dumps(qa_data.dump_squad(examples), ensure_ascii=False)
+    out_mb = len(serialized.encode('utf-8')) / (1 << 20)
+
+    # MB/s only makes sense for the stages that touch serialized bytes; the
+    # in-memory transforms report examples/s alone.
+    stages = [
+        ('parse', lambda: qa_data.parse_squad(text), in_mb),
+        ('shuffle-sentences',
+         lambda: transforms.shuffle_sentences_examples(
+             examples, random.Random(0)), None),
+        ('truncate',
+         lambda: transforms.truncate_context_examples(examples, 400), None),
+        ('chunk',
+         lambda: transforms.chunk_examples(examples, 800, 400), None),
+        ('normalize',
+         lambda: transforms.normalize_unicode_examples(examples), None),
+        ('serialize',
+         lambda: json.dumps(qa_data.dump_squad(examples),
+                            ensure_ascii=False), out_mb),
+    ]
+
+    track_memory = tracemalloc.is_tracing()
+    header = '{:<20}{:>12}{:>14}{:>10}'.format(
+        'stage', 'seconds', 'examples/s', 'MB/s')
+    if track_memory:
+        header += '{:>10}'.format('peak MB')
+    print(header)
+    for name, func, mb in stages:
+        # Best-of-N filters out scheduler noise, the usual convention for
+        # comparing across versions.
+        best = None
+        stage_peak = 0
+        for _ in range(args.iterations):
+            if track_memory:
+                tracemalloc.reset_peak()
+            begin = time.perf_counter()
+            func()
+            elapsed = time.perf_counter() - begin
+            if best is None or elapsed < best:
+                best = elapsed
+            if track_memory:
+                _, peak = tracemalloc.get_traced_memory()
+                stage_peak = max(stage_peak, peak)
+        rate = len(examples) / max(best, 1e-9)
+        throughput = ('{:.1f}'.format(mb / max(best, 1e-9))
+                      if mb is not None else '-')
+        row = '{:<20}{:>12.4f}{:>14.0f}{:>10}'.format(
+            name, best, rate, throughput)
+        if track_memory:
+            row += '{:>10.1f}'.format(stage_peak / (1 << 20))
+        print(row)
+    logging.info('Benchmarked {} examples ({:.1f} MB), best of {} runs'.format(
+        len(examples), in_mb, args.iterations))
+
+
+# This function atomically replaces a checkpoint file; a partial write after
+# a crash would make the checkpoint itself unusable.
+def _write_checkpoint(path, infile, written):
+    record = collections.OrderedDict([
+        ('input', infile),
+        ('input_size', os.path.getsize(infile)),
+        ('written', written),
+    ])
+    tmp_path = path + '.tmp'
+    with open(tmp_path, encoding='utf-8', mode='w') as f:
+        json.dump(record, f)
+    os.replace(tmp_path, path)
+
+
+# This function loads a checkpoint and returns how many examples of this
+# input were already written, or 0 when the checkpoint is absent or belongs
+# to a different input. The input is matched by path and size rather than a
+# hash: re-hashing a multi-GB corpus on every resume would cost nearly as
+# much as the restart being avoided.
+def _read_checkpoint(path, infile):
+    if not path or not os.path.isfile(path):
+        return 0
+    with open(path, encoding='utf-8') as f:
+        record = json.load(f)
+    if (record.get('input') != infile
+            or record.get('input_size') != os.path.getsize(infile)):
+        return 0
+    return record['written']
+
+
+def run_to_jsonl(args):
+    # Streams example-by-example, so arbitrarily large corpora convert in
+    # bounded memory.
+    skip = _read_checkpoint(args.checkpoint, args.infile)
+    if skip:
+        logging.info('Resuming from checkpoint: {} examples already '
+                     'written'.format(skip))
+
+    count = 0
+    with open(args.output, encoding='utf-8', mode='a' if skip else 'w') as f:
+        for example in progress.track(
+                qa_data.iter_raw_examples(args.infile, use_mmap=args.mmap),
+                'to-jsonl'):
+            count += 1
+            if count <= skip:
+                continue
+            f.write(json.dumps(example, ensure_ascii=False) + '\n')
+            if args.checkpoint and count % args.checkpoint_every == 0:
+                f.flush()
+                _write_checkpoint(args.checkpoint, args.infile, count)
+    if args.checkpoint and os.path.isfile(args.checkpoint):
+        os.remove(args.checkpoint)
+    logging.info('Wrote {} examples as JSONL -> {}'.format(count, args.output))
+
+
+def run_from_jsonl(args):
+    count = qa_data.write_squad_stream(
+        progress.track(qa_data.iter_jsonl_examples(args.infile),
+                       'from-jsonl'),
+        args.output)
+    logging.info('Read {} JSONL examples -> {}'.format(count, args.output))
+
+
+def run_to_table(args):
+    examples = read_raw_examples(args.infile)
+    qa_data.write_context_table_file(examples, args.output)
+    logging.info('Wrote {} examples with context table -> {}'.format(
+        len(examples), args.output))
+
+
+def run_from_table(args):
+    examples = qa_data.read_context_table_file(args.infile)
+    write_squad_file(examples, args.output)
+    logging.info('Expanded {} examples -> {}'.format(len(examples), args.output))
+
+
+def run_serve(args):
+    examples = read_raw_examples(args.infile)
+
+    class Handler(http.server.BaseHTTPRequestHandler):
+        # Read-only JSON endpoints over the loaded dataset; nothing here can
+        # mutate the file being served.
+        def do_GET(self):
+            parsed = urllib.parse.urlparse(self.path)
+            query = urllib.parse.parse_qs(parsed.query)
+            if parsed.path == '/ids':
+                self._send(200, list(examples))
+            elif parsed.path.startswith('/example/'):
+                example_id = urllib.parse.unquote(
+                    parsed.path[len('/example/'):])
+                if example_id in examples:
+                    self._send(200, examples[example_id])
+                else:
+                    self._send(404, {'error': 'no example {!r}'.format(
+                        example_id)})
+            elif parsed.path == '/search':
+                needle = query.get('q', [''])[0].lower()
+                limit = int(query.get('limit', ['50'])[0])
+                hits = [example_id for example_id, example in examples.items()
+                        if needle and (needle in example['question'].lower()
+                                       or needle in example['context'].lower())]
+                self._send(200, hits[:limit])
+            elif parsed.path == '/stats':
+                self._send(200, stats.compute_stats(examples))
+            else:
+                self._send(200, {'endpoints': ['/ids', '/example/<id>',
+                                               '/search?q=...&limit=50',
+                                               '/stats']})
+
+        def _send(self, status, payload):
+            body = json.dumps(payload, ensure_ascii=False).encode('utf-8')
+            self.send_response(status)
+            self.send_header('Content-Type', 'application/json; charset=utf-8')
+            self.send_header('Content-Length', str(len(body)))
+            self.end_headers()
+            self.wfile.write(body)
+
+        def log_message(self, format, *log_args):
+            logging.info('{} {}'.format(self.address_string(), format % log_args))
+
+    server = http.server.ThreadingHTTPServer(('', args.port), Handler)
+    logging.info('Serving {} examples from {} on port {} (Ctrl-C to stop)'.format(
+        len(examples), args.infile, args.port))
+    try:
+        server.serve_forever()
+    except KeyboardInterrupt:
+        pass
+
+
+def run_tui(args):
+    examples = read_raw_examples(args.infile)
+    compare = read_raw_examples(args.compare) if args.compare else None
+    tui.browse(examples, compare=compare)
+
+
+def run_review(args):
+    examples = read_raw_examples(args.infile)
+    flagged = list(examples)
+    if args.ids:
+        with open(args.ids, encoding='utf-8') as f:
+            wanted = set(line.strip() for line in f if line.strip())
+        flagged = [example_id for example_id in examples
+                   if example_id in wanted]
+
+    curated = collections.OrderedDict(examples)
+    decisions = []
+    for position, example_id in enumerate(flagged):
+        example = examples[example_id]
+        print('\n[{}/{}] {} ({})'.format(
+            position + 1, len(flagged), example_id, example['title']))
+        print('Q: {}'.format(example['question']))
+        context = example['context']
+        if example['answers']:
+            start = example['answers'][0]['answer_start']
+            end = start + len(example['answers'][0]['text'])
+            print('C: {}[[{}]]{}'.format(
+                context[:start], context[start:end], context[end:]))
+        else:
+            print('C: {}'.format(context))
+            print('(no answer)')
+
+        while True:
+            try:
+                choice = input('[a]ccept / [r]eject / [f]ix span / [s]kip '
+                               '/ [q]uit: ').strip().lower()
+            except EOFError:
+                choice = 'q'
+            if choice == 'a':
+                decisions.append((example_id, 'accept', ''))
+                break
+            elif choice == 'r':
+                del curated[example_id]
+                decisions.append((example_id, 'reject', ''))
+                break
+            elif choice == 'f':
+                try:
+                    text = input('new answer text: ').strip()
+                except EOFError:
+                    text = ''
+                found = context.find(text) if text else -1
+                if found == -1:
+                    print('not found in context; try again')
+                    continue
+                fixed = dict(example)
+                fixed['answers'] = [{'text': text, 'answer_start': found}]
+                curated[example_id] = fixed
+                decisions.append((example_id, 'fix',
+                                  '{}:{}'.format(found, text)))
+                break
+            elif choice == 's':
+                decisions.append((example_id, 'skip', ''))
+                break
+            elif choice == 'q':
+                break
+        if choice == 'q':
+            break
+
+    write_squad_file(curated, args.output)
+    log_path = os.path.splitext(args.output)[0] + '-decisions.tsv'
+    with open(log_path, encoding='utf-8', mode='w') as f:
+        for example_id, decision, detail in decisions:
+            f.write('{}\t{}\t{}\n'.format(example_id, decision, detail))
+    print('\nReviewed {} of {} flagged; kept {} examples -> {} '
+          '(decisions: {})'.format(len(decisions), len(flagged),
+                                   len(curated), args.output, log_path))
+
+
+# Top-level so multiprocessing can pickle it; returns (path, examples, error)
+# with exactly one of examples/error set.
+def _parse_dataset_file(path):
+    try:
+        if path.endswith('.jsonl'):
+            return (path, qa_data.read_jsonl_examples(path), None)
+        return (path, read_raw_examples(path), None)
+    except (ValueError, KeyError, OSError) as error:
+        return (path, None, str(error))
+
+
+def run_ingest(args):
+    paths = []
+    for directory, _, filenames in os.walk(args.directory):
+        for filename in sorted(filenames):
+            if fnmatch.fnmatch(filename, args.pattern):
+                paths.append(os.path.join(directory, filename))
+    paths.sort()
+
+    # Parsing dominates ingest time on big drops and is independent per file,
+    # so it fans out across processes; merging stays ordered and serial.
+    if args.jobs > 1 and len(paths) > 1:
+        import multiprocessing
+        with multiprocessing.Pool(args.jobs) as pool:
+            parsed = pool.map(_parse_dataset_file, paths)
+    else:
+        parsed = [_parse_dataset_file(path)
+                  for path in progress.track(paths, 'ingest',
+                                             total=len(paths))]
+
+    merged = collections.OrderedDict()
+    num_errors = 0
+    print('file\texamples\tduplicates\terror')
+    for path, examples, error in parsed:
+        if error is not None:
+            num_errors += 1
+            print('{}\t-\t-\t{}'.format(path, error))
+            continue
+        duplicates = sum(1 for example_id in examples if example_id in merged)
+        # First-seen wins across files, matching the id-keyed merge of
+        # multi-input reads.
+        for example_id, example in examples.items():
+            merged.setdefault(example_id, example)
+        print('{}\t{}\t{}\t'.format(path, len(examples), duplicates))
+
+    print('Ingested {} files: {} unique examples, {} parse errors'.format(
+        len(paths), len(merged), num_errors))
+    if args.output:
+        write_squad_file(merged, args.output)
+        print('Merged -> {}'.format(args.output))
+    if num_errors and args.strict:
+        sys.exit(EXIT_PARSE)
+
+
+def build_parser():
+    argp = argparse.ArgumentParser(
+        description='Build, augment, and analyze SQuAD-format QA datasets.')
+    argp.add_argument('-q', '--quiet', action='store_true',
+                      help='Log errors only and disable progress; outcomes '
+                           'are still reported through the exit code.')
+    argp.add_argument('-v', '--verbose', action='count', default=0,
+                      help='Increase log verbosity (repeatable). Must come '
+                           'before the subcommand, like every global flag.')
+    argp.add_argument('--log-level', default=None,
+                      choices=['debug', 'info', 'warning', 'error'],
+                      help='Set the log level explicitly (overrides -v).')
+    argp.add_argument('--log-json', action='store_true',
+                      help='Emit logs as one JSON object per line.')
+    argp.add_argument('--progress', dest='progress', action='store_true',
+                      default=None,
+                      help='Force progress reporting on (default: only when '
+                           'stderr is a terminal). Must come before the '
+                           'subcommand.')
+    argp.add_argument('--no-progress', dest='progress', action='store_false',
+                      help='Force progress reporting off.')
+    argp.add_argument('--watch', action='store_true',
+                      help='After the first build, keep monitoring the input '
+                           'files and rerun the command whenever one changes '
+                           '(Ctrl-C to stop). Must come before the '
+                           'subcommand.')
+    argp.add_argument('--json-summary', default=None, metavar='PATH',
+                      help='Write a machine-readable run summary (command, '
+                           'composition counts, output paths, timing) as '
+                           'JSON to PATH, or to stdout with "-". Must come '
+                           'before the subcommand.')
+    argp.add_argument('--force', action='store_true',
+                      help='Overwrite existing output files (by default '
+                           'qabuild refuses, so a mistyped command cannot '
+                           'clobber a finished build). Must come before the '
+                           'subcommand.')
+    argp.add_argument('--suffix', default=None, metavar='SUFFIX',
+                      help='Version output filenames instead of overwriting: '
+                           '-SUFFIX is inserted before the extension (e.g. '
+                           '--suffix v2 turns train.json into '
+                           'train-v2.json). Must come before the '
+                           'subcommand.')
+    argp.add_argument('--name-template', default=None, metavar='TEMPLATE',
+                      help='Render output filenames from a template instead '
+                           'of the configured path, e.g. '
+                           '"{stem}-{variant}-{seed}.json". Fields: {stem} '
+                           'and {ext} from the output path, {command} (and '
+                           'its alias {variant}), {seed}, plus {epoch} for '
+                           'mix --epochs. Must come before the subcommand.')
+    argp.add_argument('--answers', choices=qa_data.ANSWER_POLICIES,
+                      default='all',
+                      help='How multi-answer questions are reduced on read: '
+                           'keep every gold span (default, what eval wants) '
+                           'or collapse to one span for single-span '
+                           'training. Must come before the subcommand.')
+    argp.add_argument('--on-duplicate', choices=qa_data.DUPLICATE_POLICIES,
+                      default='error',
+                      help='What to do when two qas in one file share an id: '
+                           'abort naming the collision (default), keep one '
+                           'copy, or rename later copies with a -dupN '
+                           'suffix. Must come before the subcommand.')
+    argp.add_argument('--track-memory', action='store_true',
+                      help='Track peak RSS and Python heap usage; reported '
+                           'in the log and the --json-summary, and per stage '
+                           'in bench. Must come before the subcommand.')
+    argp.add_argument('--shards', type=int, default=1, metavar='N',
+                      help='Split the output dataset into N roughly equal '
+                           'contiguous shards named like '
+                           'train-00001-of-00008.json (the single-file '
+                           'output is removed). Applies to commands that '
+                           'write one SQuAD output. Must come before the '
+                           'subcommand.')
+    argp.add_argument('--strict', action='store_true',
+                      help='Before running, validate every SQuAD-format '
+                           'input against the expected schema and print one '
+                           'violation per line with a JSON pointer (e.g. '
+                           'data[12].paragraphs[3].qas[7].answers[0]'
+                           '.answer_start); exit {} if any are found. Must '
+                           'come before the subcommand.'.format(
+                               EXIT_VALIDATION))
+    argp.add_argument('--lenient', action='store_true',
+                      help='Skip paragraphs and qas with missing or '
+                           'malformed fields instead of aborting; skipped '
+                           'entries are counted and written (with reasons) '
+                           'to the --rejects file. Must come before the '
+                           'subcommand.')
+    argp.add_argument('--rejects', default='rejects.jsonl', metavar='PATH',
+                      help='Where --lenient writes skipped entries, one JSON '
+                           'object per line (default: %(default)s).')
+    argp.add_argument('--manifest', default=None, metavar='PATH',
+                      help='After the command finishes, write a manifest JSON '
+                           'recording the qabuild version, full arguments, '
+                           'seed, and SHA-256 of every input and output file. '
+                           'Must come before the subcommand.')
+    subparsers = argp.add_subparsers(dest='command', required=True)
+
+    synth_p = subparsers.add_parser(
+        'synth',
+        help='Synthesize AddSent-lite adversarial examples by appending a '
+             'rule-based distractor sentence (question rewritten as a statement '
+             'with the answer swapped for a same-type decoy) to each context.')
+    synth_p.add_argument('infile', metavar='INFILE',
+                         help='SQuAD-format JSON input file.')
+    synth_p.add_argument('--entities', default=None,
+                         help='TSV entity list ("type<TAB>entity" per line) used '
+                              'to pick same-type decoys for answer swapping.')
+    synth_p.add_argument('--num-distractors', type=int, default=1,
+                         help='Adversarial sentences to insert per context.')
+    synth_p.add_argument('--distractor-bank', default=None,
+                         help='JSONL distractor bank ({"sentence": ...} per '
+                              'line) to sample inserted sentences from instead '
+                              'of rewriting questions.')
+    synth_p.add_argument('--seed', type=int, default=0,
+                         help='Random seed for distractor-bank sampling.')
+    synth_p.add_argument('--position', choices=['append', 'prepend'],
+                         default='append',
+                         help='Where to insert the distractor sentence; '
+                              'prepending shifts answer offsets accordingly.')
+    synth_p.add_argument('--hash-ids', action='store_true',
+                         help='Append a deterministic hash of (id, transform, '
+                              'seed) to each new id, so reruns with the same '
+                              'parameters yield identical ids.')
+    synth_p.add_argument('-o', '--output', required=True,
+                         help='Path for the synthesized SQuAD-format output.')
+    synth_p.set_defaults(func=run_synth)
+
+    augment_p = subparsers.add_parser(
+        'augment',
+        help='Generate augmented variants of examples. Transforms are enabled '
+             'by their flags and can be combined in one invocation.')
+    augment_p.add_argument('infile', metavar='INFILE',
+                           help='SQuAD-format JSON input file.')
+    augment_p.add_argument('--gazetteer', default=None,
+                           help='TSV gazetteer ("type<TAB>entity" per line); '
+                                'entities found in distractor sentences are '
+                                'swapped for same-type alternatives.')
+    augment_p.add_argument('--perturb-numbers', action='store_true',
+                           help='Perturb numbers/years found in distractor '
+                                'sentences (+/-delta and adjacent-digit swaps).')
+    augment_p.add_argument('--number-delta', type=int, default=1,
+                           help='Offset used for +/- numeric perturbation.')
+    augment_p.add_argument('--negate', action='store_true',
+                           help='Flip the polarity of distractor sentences by '
+                                'inserting or removing negation.')
+    augment_p.add_argument('--synonyms', default=None,
+                           help='TSV synonym lexicon ("word<TAB>synonym..." per '
+                                'line); question tokens are replaced by synonyms '
+                                'at --synonym-rate.')
+    augment_p.add_argument('--synonym-rate', type=float, default=0.3,
+                           help='Per-token probability of synonym replacement.')
+    augment_p.add_argument('--paraphrase', action='store_true',
+                           help='Generate question paraphrase variants '
+                                '("-paraN" ids) from rewrite templates; '
+                                'combine with --synonyms for synonym-slot '
+                                'variants too.')
+    augment_p.add_argument('--paraphrase-rules', default=None, metavar='PATH',
+                           help='TSV of "pattern<TAB>replacement" regex '
+                                'rules replacing the built-in wh-movement '
+                                'templates.')
+    augment_p.add_argument('--backtranslate', default=None, metavar='CMD',
+                           help='Shell command doing the round-trip '
+                                'translation: questions on stdin (one per '
+                                'line), paraphrases on stdout in the same '
+                                'order. Unchanged paraphrases are dropped; '
+                                'variants get "-bt" ids.')
+    augment_p.add_argument('--backtranslate-cache', default=None,
+                           metavar='PATH',
+                           help='JSON cache of question -> paraphrase; only '
+                                'uncached questions are sent to the command '
+                                'and the file is updated afterwards.')
+    augment_p.add_argument('--case', default=None, metavar='MODES',
+                           help='Comma-separated casing modes from {} '
+                                'producing "-case<mode>" variants.'.format(
+                                    ','.join(augment.CASE_MODES)))
+    augment_p.add_argument('--case-target',
+                           choices=['question', 'context', 'both'],
+                           default='question',
+                           help='What to re-case; context re-casing keeps '
+                                'offsets and re-reads answer texts from the '
+                                're-cased context.')
+    augment_p.add_argument('--typo-rate', type=float, default=None,
+                           help='Per-character probability of injecting noise '
+                                '(swaps, drops, keyboard-adjacent substitutions).')
+    augment_p.add_argument('--typo-target', choices=['question', 'context', 'both'],
+                           default='question',
+                           help='Where to inject character noise; context noise '
+                                'never touches answer spans.')
+    augment_p.add_argument('--token-dropout', type=float, default=None,
+                           help='Per-token probability of dropping function '
+                                'words from questions.')
+    augment_p.add_argument('--dropout-protect', default=None,
+                           help='File listing tokens (one per line) that token '
+                                'dropout must never remove.')
+    augment_p.add_argument('--variants', type=int, default=3,
+                           help='Maximum variants to generate per perturbation site.')
+    augment_p.add_argument('--seed', type=int, default=0,
+                           help='Random seed for sampling perturbations.')
+    augment_p.add_argument('--hash-ids', action='store_true',
+                           help='Append a deterministic hash of (id, '
+                                'transform, seed) to each new id, so reruns '
+                                'with the same parameters yield identical ids.')
+    augment_p.add_argument('-o', '--output', required=True,
+                           help='Path for the augmented SQuAD-format output.')
+    augment_p.set_defaults(func=run_augment)
+
+    transplant_p = subparsers.add_parser(
+        'transplant',
+        help='Transplant the adversarial sentences of an AddSent-style dump '
+             'into other contexts of the same title, multiplying adversarial '
+             'coverage. Sentences are never moved into a context containing '
+             'one of their target\'s gold answers.')
+    transplant_p.add_argument('infile', metavar='CLEAN',
+                              help='SQuAD-format JSON file of clean examples '
+                                   '(the adversarial dump\'s base ids must '
+                                   'resolve here).')
+    transplant_p.add_argument('adv_file', metavar='ADVERSARIAL',
+                              help='SQuAD-format JSON file of adversarial '
+                                   'variants whose inserted sentences are '
+                                   'harvested.')
+    transplant_p.add_argument('--max-per-example', type=int, default=1,
+                              help='Maximum transplanted variants per clean '
+                                   'example.')
+    transplant_p.add_argument('--position', choices=['append', 'prepend'],
+                              default='append',
+                              help='Where the transplanted sentence goes; '
+                                   'prepend shifts answer offsets accordingly.')
+    transplant_p.add_argument('--seed', type=int, default=0,
+                              help='Random seed for choosing among harvested '
+                                   'sentences.')
+    transplant_p.add_argument('--hash-ids', action='store_true',
+                              help='Append a deterministic hash of (id, '
+                                   'transform, seed) to each new id, so reruns '
+                                   'with the same parameters yield identical '
+                                   'ids.')
+    transplant_p.add_argument('-o', '--output', required=True,
+                              help='Path for the SQuAD-format output of '
+                                   'transplanted variants.')
+    transplant_p.set_defaults(func=run_transplant)
+
+    anonymize_p = subparsers.add_parser(
+        'anonymize',
+        help='Replace person names, emails, and phone-number-like strings '
+             'with typed placeholders (answer offsets remapped; ids kept) so '
+             'internal datasets can be shared.')
+    anonymize_p.add_argument('infile', metavar='INFILE',
+                             help='SQuAD-format JSON input file.')
+    anonymize_p.add_argument('--names', default=None,
+                             help='TSV entity list of person names to redact; '
+                                  'emails and phone numbers are found by '
+                                  'pattern regardless.')
+    anonymize_p.add_argument('--mapping', default=None, metavar='PATH',
+                             help='Also write the value -> placeholder map as '
+                                  'TSV. The map re-identifies the data; keep '
+                                  'it internal.')
+    anonymize_p.add_argument('-o', '--output', required=True,
+                             help='Path for the anonymized SQuAD-format '
+                                  'output.')
+    anonymize_p.set_defaults(func=run_anonymize)
+
+    contrast_p = subparsers.add_parser(
+        'contrast',
+        help='Build counterfactual contrast sets: the gold answer entity is '
+             'swapped for another entity of the same type at every context '
+             'occurrence, with the gold answer updated to match.')
+    contrast_p.add_argument('infile', metavar='INFILE',
+                            help='SQuAD-format JSON input file.')
+    contrast_p.add_argument('--entities', required=True,
+                            help='TSV entity list ("type<TAB>entity") '
+                                 'supplying same-type swap candidates.')
+    contrast_p.add_argument('--variants', type=int, default=1,
+                            help='Maximum contrast variants per example.')
+    contrast_p.add_argument('--seed', type=int, default=0,
+                            help='Random seed for choosing among swap '
+                                 'candidates.')
+    contrast_p.add_argument('--hash-ids', action='store_true',
+                            help='Append a deterministic hash of (id, '
+                                 'transform, seed) to each new id, so reruns '
+                                 'with the same parameters yield identical '
+                                 'ids.')
+    contrast_p.add_argument('-o', '--output', required=True,
+                            help='Path for the SQuAD-format contrast-set '
+                                 'output.')
+    contrast_p.set_defaults(func=run_contrast)
+
+    distractors_p = subparsers.add_parser(
+        'distractors',
+        help='Extract the pure distractor sentences from a file holding both '
+             'clean examples and their adversarial variants, as a JSONL bank '
+             '(sentence, title, source_id) reusable via synth '
+             '--distractor-bank.')
+    distractors_p.add_argument('infile', metavar='INFILE',
+                               help='SQuAD-format JSON file containing each '
+                                    'adversarial variant alongside its base '
+                                    'example.')
+    distractors_p.add_argument('-o', '--output', required=True,
+                               help='Path for the JSONL distractor bank.')
+    distractors_p.set_defaults(func=run_distractors)
+
+    ablate_p = subparsers.add_parser(
+        'ablate',
+        help='Build diagnostic variants of a dataset (original ids are kept '
+             'so predictions can be joined against the source examples).')
+    ablate_p.add_argument('infile', metavar='INFILE',
+                          help='SQuAD-format JSON input file.')
+    ablate_p.add_argument('--mode', required=True,
+                          choices=['shuffle-sentences', 'answer-sentence-only',
+                                   'no-answer-sentence', 'question-only',
+                                   'context-only'],
+                          help='shuffle-sentences: shuffle context sentence '
+                               'order with answer offsets recomputed. '
+                               'answer-sentence-only: reduce each context to '
+                               'the sentence containing the gold answer. '
+                               'no-answer-sentence: remove the answer-bearing '
+                               'sentence and mark the example is_impossible. '
+                               'question-only / context-only: partial-input '
+                               'baselines that blank the other field.')
+    ablate_p.add_argument('--seed', type=int, default=0,
+                          help='Random seed for modes that sample.')
+    ablate_p.add_argument('-o', '--output', required=True,
+                          help='Path for the SQuAD-format output.')
+    ablate_p.set_defaults(func=run_ablate)
+
+    truncate_p = subparsers.add_parser(
+        'truncate',
+        help='Truncate long contexts to a window centered on the answer span, '
+             'recomputing offsets.')
+    truncate_p.add_argument('infile', metavar='INFILE',
+                            help='SQuAD-format JSON input file.')
+    truncate_p.add_argument('--max-context-chars', type=int, required=True,
+                            help='Maximum context length in characters.')
+    truncate_p.add_argument('-o', '--output', required=True,
+                            help='Path for the SQuAD-format output.')
+    truncate_p.set_defaults(func=run_truncate)
+
+    concat_p = subparsers.add_parser(
+        'concat',
+        help='Concatenate all paragraphs of a title into one long context per '
+             'question (offsets adjusted), for long-context experiments.')
+    concat_p.add_argument('infile', metavar='INFILE',
+                          help='SQuAD-format JSON input file.')
+    concat_p.add_argument('-o', '--output', required=True,
+                          help='Path for the SQuAD-format output.')
+    concat_p.set_defaults(func=run_concat)
+
+    chunk_p = subparsers.add_parser(
+        'chunk',
+        help='Split long contexts into overlapping sliding-window chunks; '
+             'chunks not containing the answer are marked is_impossible.')
+    chunk_p.add_argument('infile', metavar='INFILE',
+                         help='SQuAD-format JSON input file.')
+    chunk_p.add_argument('--max-len', type=int, required=True,
+                         help='Maximum chunk length in characters.')
+    chunk_p.add_argument('--stride', type=int, required=True,
+                         help='Window advance in characters between chunks.')
+    chunk_p.add_argument('--drop-empty', action='store_true',
+                         help='Drop chunks that do not contain an answer '
+                              'instead of emitting them as unanswerable.')
+    chunk_p.add_argument('-o', '--output', required=True,
+                         help='Path for the SQuAD-format output.')
+    chunk_p.set_defaults(func=run_chunk)
+
+    stats_p = subparsers.add_parser(
+        'stats',
+        help='Print dataset summary statistics (counts, length distributions; '
+             'token-level lengths when --tokenizer is given).')
+    stats_p.add_argument('infiles', metavar='INFILE', nargs='+',
+                         help='SQuAD-format JSON input files or glob '
+                              'patterns; several inputs are merged into one '
+                              'pool with per-file source tracking.')
+    stats_p.add_argument('--tokenizer', default=None,
+                         help='tokenizer.json path or HuggingFace model ID; '
+                              'enables subword token-count statistics.')
+    stats_p.add_argument('--token-limit', type=int, default=512,
+                         help='Report how many question+context pairs exceed '
+                              'this many tokens.')
+    stats_p.set_defaults(func=run_stats)
+
+    export_features_p = subparsers.add_parser(
+        'export-features',
+        help='Tokenize and export ready-to-train QA features (input_ids, '
+             'attention_mask, start/end positions) to an .npz file.')
+    export_features_p.add_argument('infiles', metavar='INFILE', nargs='+',
+                                   help='SQuAD-format JSON input files or '
+                                        'glob patterns, merged into one '
+                                        'pool.')
+    export_features_p.add_argument('--tokenizer', required=True,
+                                   help='HuggingFace model ID or checkpoint '
+                                        'directory with a fast tokenizer.')
+    export_features_p.add_argument('-o', '--output', required=True,
+                                   help='Path for the .npz feature archive.')
+    export_features_p.set_defaults(func=run_export_features)
+
+    index_p = subparsers.add_parser(
+        'index',
+        help='Build an on-disk BM25 index over the unique paragraphs of a '
+             'dataset, for negative mining and retrieval exports.')
+    index_p.add_argument('infiles', metavar='INFILE', nargs='+',
+                         help='SQuAD-format JSON input files or glob '
+                              'patterns, merged into one corpus.')
+    index_p.add_argument('-o', '--output', required=True,
+                         help='Path for the JSON index file.')
+    index_p.set_defaults(func=run_index)
+
+    search_p = subparsers.add_parser(
+        'search', help='Query a BM25 index built with the index command.')
+    search_p.add_argument('index', metavar='INDEX',
+                          help='Index file produced by the index command.')
+    search_p.add_argument('query', metavar='QUERY', help='Query text.')
+    search_p.add_argument('-k', '--top-k', type=int, default=10,
+                          help='Number of results to print.')
+    search_p.set_defaults(func=run_search)
+
+    mine_negatives_p = subparsers.add_parser(
+        'mine-negatives',
+        help='Mine BM25 hard-negative paragraphs per question (none containing '
+             'a gold answer) and attach them as DPR-style JSONL or append them '
+             'to contexts as distractor paragraphs.')
+    mine_negatives_p.add_argument('infile', metavar='INFILE',
+                                  help='SQuAD-format JSON input file.')
+    mine_negatives_p.add_argument('--index', required=True,
+                                  help='Index file produced by the index command.')
+    mine_negatives_p.add_argument('-k', '--top-k', type=int, default=5,
+                                  help='Hard negatives to mine per question.')
+    mine_negatives_p.add_argument('--mode', choices=['attach', 'append'],
+                                  default='attach',
+                                  help='attach: JSONL with hard_negatives per '
+                                       'question. append: SQuAD output with '
+                                       'negatives appended to each context.')
+    mine_negatives_p.add_argument('-o', '--output', required=True,
+                                  help='Path for the output file.')
+    mine_negatives_p.set_defaults(func=run_mine_negatives)
+
+    retrieval_set_p = subparsers.add_parser(
+        'retrieval-set',
+        help='Build an open-book multi-passage dataset: each question paired '
+             'with its gold paragraph plus top-k retrieved paragraphs, '
+             'shuffled, with the gold position recorded.')
+    retrieval_set_p.add_argument('infile', metavar='INFILE',
+                                 help='SQuAD-format JSON input file.')
+    retrieval_set_p.add_argument('--index', required=True,
+                                 help='Index file produced by the index command.')
+    retrieval_set_p.add_argument('-k', '--top-k', type=int, default=5,
+                                 help='Retrieved paragraphs per question (plus gold).')
+    retrieval_set_p.add_argument('--seed', type=int, default=0,
+                                 help='Random seed for passage shuffling.')
+    retrieval_set_p.add_argument('-o', '--output', required=True,
+                                 help='Path for the JSONL output.')
+    retrieval_set_p.set_defaults(func=run_retrieval_set)
+
+    export_fid_p = subparsers.add_parser(
+        'export-fid',
+        help='Export Fusion-in-Decoder records (question, answers, list of '
+             'ctxs with title/text) with retrieved passages from a BM25 index.')
+    export_fid_p.add_argument('infile', metavar='INFILE',
+                              help='SQuAD-format JSON input file.')
+    export_fid_p.add_argument('--index', required=True,
+                              help='Index file produced by the index command.')
+    export_fid_p.add_argument('-k', '--top-k', type=int, default=20,
+                              help='Passages per question (including gold).')
+    export_fid_p.add_argument('-o', '--output', required=True,
+                              help='Path for the FiD-format JSON output.')
+    export_fid_p.set_defaults(func=run_export_fid)
+
+    swap_negatives_p = subparsers.add_parser(
+        'swap-negatives',
+        help='Pair questions with plausible but wrong contexts from the same '
+             'title (never containing a gold answer) and mark them '
+             'is_impossible, generating SQuAD 2.0-style negatives.')
+    swap_negatives_p.add_argument('infile', metavar='INFILE',
+                                  help='SQuAD-format JSON input file.')
+    swap_negatives_p.add_argument('--seed', type=int, default=0,
+                                  help='Random seed for context selection.')
+    swap_negatives_p.add_argument('-o', '--output', required=True,
+                                  help='Path for the SQuAD-format output.')
+    swap_negatives_p.set_defaults(func=run_swap_negatives)
+
+    convert_p = subparsers.add_parser(
+        'convert',
+        help='Convert between the SQuAD 1.1 and 2.0 schemas.')
+    convert_p.add_argument('infile', metavar='INFILE',
+                           help='SQuAD-format JSON input file.')
+    convert_p.add_argument('--to', required=True, choices=['v1.1', 'v2.0'],
+                           help='Target schema version.')
+    convert_p.add_argument('--offset-unit-in', choices=qa_data.OFFSET_UNITS,
+                           default='chars',
+                           help='Unit answer_start is counted in within the '
+                                'input file (XQuAD/MLQA exports often use '
+                                'bytes or UTF-16 code units).')
+    convert_p.add_argument('--offset-unit-out', choices=qa_data.OFFSET_UNITS,
+                           default='chars',
+                           help='Unit to count answer_start in on output.')
+    convert_p.add_argument('--keep-impossible', action='store_true',
+                           help='When downgrading to v1.1, keep unanswerable '
+                                'questions (with empty answers) instead of '
+                                'dropping them.')
+    convert_p.add_argument('-o', '--output', required=True,
+                           help='Path for the converted output.')
+    convert_p.set_defaults(func=run_convert)
+
+    import_nq_p = subparsers.add_parser(
+        'import-nq',
+        help='Import Natural Questions simplified-format JSONL, mapping short '
+             'answers to SQuAD-style spans within the long answer.')
+    import_nq_p.add_argument('infile', metavar='INFILE',
+                             help='NQ simplified-format JSONL input file.')
+    import_nq_p.add_argument('-o', '--output', required=True,
+                             help='Path for the SQuAD-format output.')
+    import_nq_p.set_defaults(func=run_import_nq)
+
+    import_triviaqa_p = subparsers.add_parser(
+        'import-triviaqa',
+        help='Import TriviaQA-format JSON, locating answer aliases in the '
+             'evidence documents to synthesize answer_start offsets.')
+    import_triviaqa_p.add_argument('infile', metavar='INFILE',
+                                   help='TriviaQA-format JSON input file.')
+    import_triviaqa_p.add_argument('--evidence-dir', required=True,
+                                   help='Directory containing the evidence text '
+                                        'files referenced by the input.')
+    import_triviaqa_p.add_argument('-o', '--output', required=True,
+                                   help='Path for the SQuAD-format output.')
+    import_triviaqa_p.set_defaults(func=run_import_triviaqa)
+
+    import_newsqa_p = subparsers.add_parser(
+        'import-newsqa',
+        help='Import NewsQA (combined JSON or CSV form) using the consensus/'
+             'annotated answer char ranges.')
+    import_newsqa_p.add_argument('infile', metavar='INFILE',
+                                 help='NewsQA JSON or CSV input file.')
+    import_newsqa_p.add_argument('--validated-only', action='store_true',
+                                 help='Keep only questions with a validated/'
+                                      'consensus answer.')
+    import_newsqa_p.add_argument('-o', '--output', required=True,
+                                 help='Path for the SQuAD-format output.')
+    import_newsqa_p.set_defaults(func=run_import_newsqa)
+
+    normalize_p = subparsers.add_parser(
+        'normalize',
+        help='Normalize contexts and questions (Unicode NFC/NFKC) with answer '
+             'offsets remapped.')
+    normalize_p.add_argument('infile', metavar='INFILE',
+                             help='SQuAD-format JSON input file.')
+    normalize_p.add_argument('--form', choices=['NFC', 'NFKC'], default=None,
+                             help='Unicode normalization form to apply.')
+    normalize_p.add_argument('--whitespace', action='store_true',
+                             help='Collapse repeated whitespace/newlines in '
+                                  'contexts, adjusting answer offsets.')
+    normalize_p.add_argument('-o', '--output', required=True,
+                             help='Path for the SQuAD-format output.')
+    normalize_p.set_defaults(func=run_normalize)
+
+    filter_p = subparsers.add_parser(
+        'filter',
+        help='Drop annotation-noise answers by length bounds '
+             '(single-character clicks, whole-sentence drags); examples '
+             'losing every answer are dropped.')
+    filter_p.add_argument('infile', metavar='INFILE',
+                          help='SQuAD-format JSON input file.')
+    filter_p.add_argument('--min-answer-chars', type=int, default=None,
+                          help='Drop answers shorter than this many '
+                               'characters.')
+    filter_p.add_argument('--max-answer-chars', type=int, default=None,
+                          help='Drop answers longer than this many '
+                               'characters.')
+    filter_p.add_argument('--min-answer-tokens', type=int, default=None,
+                          help='Drop answers with fewer whitespace tokens '
+                               'than this.')
+    filter_p.add_argument('--max-answer-tokens', type=int, default=None,
+                          help='Drop answers with more whitespace tokens '
+                               'than this.')
+    filter_p.add_argument('-o', '--output', required=True,
+                          help='Path for the filtered SQuAD-format output.')
+    filter_p.set_defaults(func=run_filter)
+
+    curriculum_p = subparsers.add_parser(
+        'curriculum',
+        help='Emit a series of training files with increasing adversarial '
+             'fraction (plus a manifest), for curriculum-learning experiments.')
+    curriculum_p.add_argument('clean', metavar='CLEAN',
+                              help='Clean SQuAD-format JSON input file.')
+    curriculum_p.add_argument('adversarial', metavar='ADVERSARIAL',
+                              help='Adversarial variants (suffixed ids) of the '
+                                   'clean examples.')
+    curriculum_p.add_argument('--fractions', default='0,0.2,0.4,0.6',
+                              help='Comma-separated adversarial fractions, one '
+                                   'output file per fraction.')
+    curriculum_p.add_argument('--target', action='store_true',
+                              help='Treat --fractions as target output '
+                                   'compositions instead of per-example '
+                                   'probabilities; the probabilities are '
+                                   'derived from how many examples actually '
+                                   'have variants.')
+    curriculum_p.add_argument('--seed', type=int, default=0,
+                              help='Seed shared by all stages.')
+    curriculum_p.add_argument('-o', '--output-dir', required=True,
+                              help='Directory for stage files and manifest.')
+    curriculum_p.set_defaults(func=run_curriculum)
+
+    mix_p = subparsers.add_parser(
+        'mix',
+        help='Mix a clean dataset with adversarial variants at a given '
+             'fraction; --epochs writes several independently sampled copies.')
+    mix_p.add_argument('clean', metavar='CLEAN',
+                       help='Clean SQuAD-format JSON input file.')
+    mix_p.add_argument('adversarial', metavar='ADVERSARIAL',
+                       help='Adversarial variants (suffixed ids) of the clean '
+                            'examples.')
+    mix_p.add_argument('--fraction', type=float, default=None,
+                       help='Probability of replacing a clean example with an '
+                            'adversarial variant.')
+    mix_p.add_argument('--target-fraction', type=float, default=None,
+                       help='Desired adversarial fraction of the OUTPUT; the '
+                            'per-example replacement probability is derived '
+                            'from how many clean examples actually have '
+                            'variants, so it generalizes to any input size. '
+                            'Mutually exclusive with --fraction.')
+    mix_p.add_argument('--title-fractions', default=None,
+                       help='TSV file ("title<TAB>fraction" per line) of '
+                            'per-article fraction overrides; unlisted titles '
+                            'use --fraction.')
+    mix_p.add_argument('--keep-variant-ids', action='store_true',
+                       help='Preserve the suffixed variant id in the output '
+                            'instead of collapsing it to the base id.')
+    mix_p.add_argument('--union', action='store_true',
+                       help='Keep both the clean and the adversarial copy of '
+                            'each example (the variant keeps its suffixed id) '
+                            'instead of sampling one per id.')
+    mix_p.add_argument('--epochs', type=int, default=1,
+                       help='Number of epoch-shuffled copies to write, each '
+                            'with an independent RNG stream from the seed.')
+    mix_p.add_argument('--seed', type=int, default=0,
+                       help='Random seed for the mixing draws.')
+    mix_p.add_argument('-o', '--output', required=True,
+                       help='Output path ("-epochN" is inserted when '
+                            '--epochs > 1).')
+    mix_p.set_defaults(func=run_mix)
+
+    sample_p = subparsers.add_parser(
+        'sample',
+        help='Draw a weighted sample of examples without replacement; weights '
+             'come from a TSV file so hard examples can be upweighted.')
+    sample_p.add_argument('infile', metavar='INFILE',
+                          help='SQuAD-format JSON input file.')
+    sample_p.add_argument('--num', type=int, default=None,
+                          help='Number of examples to keep.')
+    sample_p.add_argument('--per-type', type=int, default=None,
+                          help='Instead of --num, keep up to this many '
+                               'examples per question type (who/what/'
+                               'when/...), balancing the output across '
+                               'types.')
+    sample_p.add_argument('--length-buckets', default=None, metavar='EDGES',
+                          help='Comma-separated context-length edges (chars) '
+                               'defining buckets to stratify --num over, so '
+                               'short and long contexts stay represented.')
+    sample_p.add_argument('--length-mode',
+                          choices=['proportional', 'uniform'],
+                          default='proportional',
+                          help='How --num is split across length buckets: by '
+                               'population share, or equally per bucket.')
+    sample_p.add_argument('--weights', default=None,
+                          help='TSV weights file ("id<TAB>weight" per line); '
+                               'unlisted ids weigh 1.0, non-positive weights '
+                               'exclude the example. Omit for a uniform '
+                               'sample.')
+    sample_p.add_argument('--seed', type=int, default=0,
+                          help='Random seed for the sampling draws.')
+    sample_p.add_argument('-o', '--output', required=True,
+                          help='Output SQuAD-format JSON file.')
+    sample_p.set_defaults(func=run_sample)
+
+    stratify_p = subparsers.add_parser(
+        'stratify',
+        help='Draw an evaluation subset balanced by difficulty: bucket '
+             'questions by their per-id F1 score and sample each bucket '
+             'uniformly.')
+    stratify_p.add_argument('infile', metavar='INFILE',
+                            help='SQuAD-format JSON input file.')
+    stratify_p.add_argument('--scores', required=True,
+                            help='Per-question score IDFILE '
+                                 '("id<TAB>f1" per line).')
+    stratify_p.add_argument('--buckets', default='0,0.5',
+                            help='Comma-separated ascending bucket edges; the '
+                                 'first edge is inclusive (0 isolates F1=0), '
+                                 'the rest exclusive. The default gives F1=0, '
+                                 '0<F1<0.5, and F1>=0.5.')
+    stratify_p.add_argument('--per-bucket', type=int, required=True,
+                            help='Examples to draw from each bucket.')
+    stratify_p.add_argument('--seed', type=int, default=0,
+                            help='Random seed for the bucket draws.')
+    stratify_p.add_argument('-o', '--output', required=True,
+                            help='Output SQuAD-format JSON file.')
+    stratify_p.set_defaults(func=run_stratify)
+
+    mine_p = subparsers.add_parser(
+        'mine',
+        help='Extract the examples a model scored poorly on into their own '
+             'dataset for focused fine-tuning.')
+    mine_p.add_argument('infile', metavar='INFILE',
+                        help='SQuAD-format JSON input file.')
+    mine_p.add_argument('idfile', metavar='IDFILE',
+                        help='Per-question score IDFILE ("id<TAB>f1" per line).')
+    mine_p.add_argument('--f1-below', type=float, default=0.5,
+                        help='Keep examples whose F1 is strictly below this '
+                             'threshold.')
+    mine_p.add_argument('--clean-scores', default=None,
+                        help='Per-question IDFILE of clean-dataset scores. '
+                             'When given, only "model-fooled" examples are '
+                             'kept: wrong on the (adversarial) input, but '
+                             'right (F1 at or above the threshold) on the '
+                             'clean base example.')
+    mine_p.add_argument('-o', '--output', required=True,
+                        help='Output SQuAD-format JSON file.')
+    mine_p.set_defaults(func=run_mine)
+
+    ensemble_p = subparsers.add_parser(
+        'ensemble',
+        help='Merge per-question score IDFILEs from several model runs into '
+             'one consensus IDFILE for mining/stratification.')
+    ensemble_p.add_argument('idfiles', metavar='IDFILE', nargs='+',
+                            help='Per-question score IDFILEs to merge.')
+    ensemble_p.add_argument('--mode',
+                            choices=['mean', 'min', 'majority'],
+                            default='mean',
+                            help='Consensus rule: mean score, worst-case '
+                                 'score, or majority-correct (1.0/0.0 by '
+                                 'threshold vote).')
+    ensemble_p.add_argument('--threshold', type=float, default=0.5,
+                            help='Correctness threshold for majority voting.')
+    ensemble_p.add_argument('-o', '--output', required=True,
+                            help='Output consensus IDFILE (TSV).')
+    ensemble_p.set_defaults(func=run_ensemble)
+
+    setop_p = subparsers.add_parser(
+        'setop',
+        help='Set algebra on example ids: union, intersection, or difference '
+             'of two datasets (the left operand wins on overlapping ids).')
+    setop_p.add_argument('op', choices=['union', 'intersect', 'diff'],
+                         help='Operation to apply (diff keeps A ids not in B).')
+    setop_p.add_argument('file_a', metavar='A',
+                         help='Left-operand SQuAD-format JSON file.')
+    setop_p.add_argument('file_b', metavar='B',
+                         help='Right-operand SQuAD-format JSON file.')
+    setop_p.add_argument('-o', '--output', required=True,
+                         help='Output SQuAD-format JSON file.')
+    setop_p.set_defaults(func=run_setop)
+
+    remap_p = subparsers.add_parser(
+        'remap-ids',
+        help='Rewrite example ids with a template or a mapping file so '
+             'multi-source merges never collide; writes the applied '
+             'old-to-new mapping alongside the output.')
+    remap_p.add_argument('infile', metavar='INFILE',
+                         help='SQuAD-format JSON input file.')
+    remap_p.add_argument('--template', default='{id}',
+                         help='New-id template with an {id} placeholder, e.g. '
+                              '"addsent1-{id}".')
+    remap_p.add_argument('--mapping', default=None,
+                         help='TSV mapping file ("old<TAB>new" per line); '
+                              'unlisted ids pass through. Overrides '
+                              '--template.')
+    remap_p.add_argument('-o', '--output', required=True,
+                         help='Output SQuAD-format JSON file (the mapping is '
+                              'written to "<output stem>-idmap.tsv").')
+    remap_p.set_defaults(func=run_remap_ids)
+
+    verify_p = subparsers.add_parser(
+        'verify',
+        help='Re-hash every file referenced by a build manifest and report '
+             'drift; exits nonzero if any file changed or is missing.')
+    verify_p.add_argument('manifest_file', metavar='MANIFEST',
+                          help='Manifest JSON written by a --manifest run.')
+    verify_p.set_defaults(func=run_verify)
+
+    repro_p = subparsers.add_parser(
+        'repro',
+        help='Re-run the build recorded in a manifest into a temp directory '
+             'and diff the outputs against the recorded checksums.')
+    repro_p.add_argument('manifest_file', metavar='MANIFEST',
+                         help='Manifest JSON written by a --manifest run.')
+    repro_p.set_defaults(func=run_repro)
+
+    run_p = subparsers.add_parser(
+        'run',
+        help='Execute an ordered pipeline of steps from a TOML config, so '
+             'multi-output builds live in version control instead of shell '
+             'scripts.')
+    run_p.add_argument('config', metavar='PIPELINE',
+                       help='TOML file with [[step]] tables; each table sets '
+                            '"command" plus that command\'s arguments as keys '
+                            '(positional names as-is, option names with '
+                            'underscores for dashes, booleans for flags).')
+    run_p.set_defaults(func=run_pipeline)
+
+    exec_p = subparsers.add_parser(
+        'exec',
+        help='Pipe examples as JSONL through an external command and read '
+             'transformed examples back, so custom transforms (e.g. a '
+             'paraphraser) can plug in without forking qabuild. Returned '
+             'answer offsets are verified against the returned contexts.')
+    exec_p.add_argument('infile', metavar='INFILE',
+                        help='SQuAD-format JSON input file.')
+    exec_p.add_argument('cmd', metavar='COMMAND',
+                        help='Shell command reading example JSONL on stdin '
+                             'and writing example JSONL on stdout; it may '
+                             'drop, modify, or add examples.')
+    exec_p.add_argument('-o', '--output', required=True,
+                        help='Output SQuAD-format JSON file.')
+    exec_p.set_defaults(func=run_exec)
+
+    plugin_p = subparsers.add_parser(
+        'plugin',
+        help='Apply a transform plugin (a Python module defining '
+             'transform(example)) discovered from a plugins directory; '
+             'usable as a pipeline step like any other command.')
+    plugin_p.add_argument('infile', metavar='INFILE',
+                          help='SQuAD-format JSON input file.')
+    plugin_p.add_argument('name', metavar='NAME',
+                          help='Plugin name (the module filename without '
+                               '.py).')
+    plugin_p.add_argument('--plugins-dir', default='plugins',
+                          help='Directory to discover plugin modules in.')
+    plugin_p.add_argument('-o', '--output', required=True,
+                          help='Output SQuAD-format JSON file.')
+    plugin_p.set_defaults(func=run_plugin)
+
+    to_jsonl_p = subparsers.add_parser(
+        'to-jsonl',
+        help='Flatten a SQuAD-format file to one example object per line, '
+             'the interchange format for non-Python consumers and external '
+             'transforms.')
+    to_jsonl_p.add_argument('infile', metavar='INFILE',
+                            help='SQuAD-format JSON input file.')
+    to_jsonl_p.add_argument('--mmap', action='store_true',
+                            help='Memory-map the input instead of buffered '
+                                 'reads (cheapest way through multi-GB '
+                                 'files).')
+    to_jsonl_p.add_argument('--checkpoint', default=None, metavar='PATH',
+                            help='Periodically record progress to PATH so an '
+                                 'interrupted conversion resumes (rerun the '
+                                 'same command) instead of restarting. '
+                                 'Removed on success.')
+    to_jsonl_p.add_argument('--checkpoint-every', type=int, default=10000,
+                            metavar='N',
+                            help='Examples between checkpoint updates '
+                                 '(default: %(default)s).')
+    to_jsonl_p.add_argument('-o', '--output', required=True,
+                            help='Output JSONL file.')
+    to_jsonl_p.set_defaults(func=run_to_jsonl)
+
+    from_jsonl_p = subparsers.add_parser(
+        'from-jsonl',
+        help='Re-nest example JSONL back into the SQuAD format.')
+    from_jsonl_p.add_argument('infile', metavar='INFILE',
+                              help='Example JSONL input file.')
+    from_jsonl_p.add_argument('-o', '--output', required=True,
+                              help='Output SQuAD-format JSON file.')
+    from_jsonl_p.set_defaults(func=run_from_jsonl)
+
+    to_table_p = subparsers.add_parser(
+        'to-table',
+        help='Write the normalized context-table format: contexts stored '
+             'once, referenced by index from examples (3-5x smaller on '
+             'paragraph-heavy datasets).')
+    to_table_p.add_argument('infile', metavar='INFILE',
+                            help='SQuAD-format JSON input file.')
+    to_table_p.add_argument('-o', '--output', required=True,
+                            help='Output context-table JSON file.')
+    to_table_p.set_defaults(func=run_to_table)
+
+    from_table_p = subparsers.add_parser(
+        'from-table',
+        help='Re-expand a context-table file into the SQuAD format.')
+    from_table_p.add_argument('infile', metavar='INFILE',
+                              help='Context-table JSON input file.')
+    from_table_p.add_argument('-o', '--output', required=True,
+                              help='Output SQuAD-format JSON file.')
+    from_table_p.set_defaults(func=run_from_table)
+
+    export_hits_p = subparsers.add_parser(
+        'export-hits',
+        help='Emit MTurk-style CSV batches (one row per HIT: id, title, '
+             'question, context, current answer) for re-annotating flagged '
+             'examples.')
+    export_hits_p.add_argument('infile', metavar='INFILE',
+                               help='SQuAD-format JSON input file.')
+    export_hits_p.add_argument('--ids', default=None,
+                               help='File listing ids to export (one per '
+                                    'line); default is every example.')
+    export_hits_p.add_argument('--batch-size', type=int, default=100,
+                               help='HITs per CSV batch (default: '
+                                    '%(default)s).')
+    export_hits_p.add_argument('-o', '--output', required=True,
+                               help='Output stem; batches are written as '
+                                    '"<stem>-batch-001.csv" etc.')
+    export_hits_p.set_defaults(func=run_export_hits)
+
+    agreement_p = subparsers.add_parser(
+        'agreement',
+        help='Compute inter-annotator agreement (pairwise exact match, '
+             'token F1, span overlap) over questions with multiple gold '
+             'answers, overall and per title.')
+    agreement_p.add_argument('infile', metavar='INFILE',
+                             help='SQuAD-format JSON input file.')
+    agreement_p.add_argument('--normalize',
+                             default=','.join(stats.NORMALIZE_STEPS),
+                             metavar='STEPS',
+                             help='Comma-separated answer normalization '
+                                  'steps applied before comparing (default: '
+                                  '%(default)s; pass "" for none). Numeric '
+                                  'answers want punctuation kept; '
+                                  'non-English sets have no articles to '
+                                  'strip.')
+    agreement_p.add_argument('--tokenize', default='whitespace',
+                             metavar='MODE',
+                             help='Tokenization for F1: "whitespace" '
+                                  '(default, the official SQuAD script), '
+                                  '"regex" (\\w+ runs), or a tokenizer.json '
+                                  'file / HuggingFace model ID for subword '
+                                  'tokens.')
+    agreement_p.add_argument('--per-question', default=None, metavar='PATH',
+                             help='Also write per-question agreement values '
+                                  'as TSV, for pulling low-agreement '
+                                  'questions into review.')
+    agreement_p.set_defaults(func=run_agreement)
+
+    calibration_p = subparsers.add_parser(
+        'calibration',
+        help='Reliability-bin statistics (accuracy per confidence decile, '
+             'ECE) from a predictions file with confidences, split clean '
+             'vs adversarial by variant-id matching.')
+    calibration_p.add_argument('infile', metavar='INFILE',
+                               help='SQuAD-format JSON input file.')
+    calibration_p.add_argument('predictions', metavar='PREDICTIONS',
+                               help='JSON map id -> {text, confidence} '
+                                    '(logits are squashed to '
+                                    'probabilities).')
+    calibration_p.add_argument('--bins', type=int, default=10,
+                               help='Number of fixed-width confidence bins '
+                                    '(default: %(default)s).')
+    calibration_p.set_defaults(func=run_calibration)
+
+    score_p = subparsers.add_parser(
+        'score',
+        help='Score a predictions file (EM/F1) with breakdowns by title, '
+             'by clean/adversarial subset, and by categorical fields from '
+             'a sidecar metadata file.')
+    score_p.add_argument('infile', metavar='INFILE',
+                         help='SQuAD-format JSON input file.')
+    score_p.add_argument('predictions', metavar='PREDICTIONS',
+                         help='JSON map id -> predicted answer text (the '
+                              'HF predictions.json layout).')
+    score_p.add_argument('--metadata', default=None, metavar='PATH',
+                         help='Sidecar id -> categorical fields (JSON map '
+                              'or headed TSV); each field becomes a '
+                              'breakdown.')
+    score_p.add_argument('--csv', default=None, metavar='PATH',
+                         help='Also write the breakdown rows as CSV.')
+    score_p.set_defaults(func=run_score)
+
+    contamination_p = subparsers.add_parser(
+        'contamination',
+        help='Check n-gram overlap between training contexts and a '
+             'held-out eval file; prints contaminated id pairs and exits '
+             '{} if any are found.'.format(EXIT_VALIDATION))
+    contamination_p.add_argument('infile', metavar='INFILE',
+                                 help='SQuAD-format training file to check.')
+    contamination_p.add_argument('eval_file', metavar='EVALFILE',
+                                 help='Held-out SQuAD-format eval file.')
+    contamination_p.add_argument('--ngram', type=int, default=13,
+                                 help='Overlap n-gram size (default: '
+                                      '%(default)s, the usual convention).')
+    contamination_p.set_defaults(func=run_contamination)
+
+    delta_p = subparsers.add_parser(
+        'delta',
+        help='Pair base ids with their suffixed variants and report '
+             'per-pair F1 deltas, listing the biggest regressions with '
+             'their contexts.')
+    delta_p.add_argument('infile', metavar='INFILE',
+                         help='SQuAD-format JSON file containing both base '
+                              'and variant examples.')
+    delta_p.add_argument('predictions', metavar='PREDICTIONS',
+                         help='JSON map id -> predicted answer text.')
+    delta_p.add_argument('--top', type=int, default=10,
+                         help='Regressions listed in full (default: '
+                              '%(default)s).')
+    delta_p.set_defaults(func=run_delta)
+
+    nbest_p = subparsers.add_parser(
+        'nbest',
+        help='Oracle and rank analysis over HuggingFace '
+             'nbest_predictions.json: is the gold answer proposed in the '
+             'top-k, and how is it ranked?')
+    nbest_p.add_argument('infile', metavar='INFILE',
+                         help='SQuAD-format JSON input file.')
+    nbest_p.add_argument('nbest', metavar='NBEST',
+                         help='nbest_predictions.json (id -> candidate '
+                              'list).')
+    nbest_p.add_argument('--top-k', type=int, default=20,
+                         help='Candidates considered per question '
+                              '(default: %(default)s, the HF n_best_size).')
+    nbest_p.set_defaults(func=run_nbest)
+
+    bench_p = subparsers.add_parser(
+        'bench',
+        help='Time parse, representative transforms, and serialization over '
+             'a dataset and print throughput, so performance regressions '
+             'across versions are measurable.')
+    bench_p.add_argument('infile', metavar='INFILE',
+                         help='SQuAD-format JSON input file.')
+    bench_p.add_argument('--iterations', type=int, default=3,
+                         help='Timed runs per stage; the best is reported '
+                              '(default: %(default)s).')
+    bench_p.set_defaults(func=run_bench)
+
+    serve_p = subparsers.add_parser(
+        'serve',
+        help='Serve a dataset read-only over HTTP (list ids, fetch examples, '
+             'substring search, stats) for browsing without copying files.')
+    serve_p.add_argument('infile', metavar='INFILE',
+                         help='SQuAD-format JSON input file.')
+    serve_p.add_argument('--port', type=int, default=8080,
+                         help='TCP port to listen on.')
+    serve_p.set_defaults(func=run_serve)
+
+    tui_p = subparsers.add_parser(
+        'tui',
+        help='Browse a dataset in the terminal: searchable example list, '
+             'answer spans highlighted in the context, and side-by-side '
+             'clean/adversarial views with --compare.')
+    tui_p.add_argument('infile', metavar='INFILE',
+                       help='SQuAD-format JSON input file.')
+    tui_p.add_argument('--compare', default=None,
+                       help='Adversarial variants file; the matched variant '
+                            'is shown below each clean example.')
+    tui_p.set_defaults(func=run_tui)
+
+    review_p = subparsers.add_parser(
+        'review',
+        help='Step through flagged examples interactively, accepting, '
+             'rejecting, or fixing answer spans; writes the curated dataset '
+             'and a decisions log.')
+    review_p.add_argument('infile', metavar='INFILE',
+                          help='SQuAD-format JSON input file.')
+    review_p.add_argument('--ids', default=None,
+                          help='File listing ids to review (one per line); '
+                               'default is every example. Unreviewed examples '
+                               'pass through unchanged.')
+    review_p.add_argument('-o', '--output', required=True,
+                          help='Curated output file (the decisions log goes '
+                               'to "<output stem>-decisions.tsv").')
+    review_p.set_defaults(func=run_review)
+
+    ingest_p = subparsers.add_parser(
+        'ingest',
+        help='Scan a directory tree for dataset files, report per-file '
+             'examples/duplicates/parse errors, and optionally merge '
+             'everything into one dataset.')
+    ingest_p.add_argument('directory', metavar='DIR',
+                          help='Directory to scan recursively.')
+    ingest_p.add_argument('--pattern', default='*.json',
+                          help='Filename pattern to ingest (*.jsonl files '
+                               'are read as example JSONL).')
+    ingest_p.add_argument('--jobs', type=int, default=1,
+                          help='Worker processes for parallel file parsing.')
+    # SUPPRESS keeps the global --strict value when the local flag is absent
+    # (both write the same namespace slot).
+    ingest_p.add_argument('--strict', action='store_true',
+                          default=argparse.SUPPRESS,
+                          help='Exit nonzero if any file failed to parse.')
+    ingest_p.add_argument('-o', '--output', default=None,
+                          help='Optional merged SQuAD-format output '
+                               '(first-seen example wins on duplicate ids).')
+    ingest_p.set_defaults(func=run_ingest)
+
+    return argp, subparsers
+
+
+# Structured log formatter for --log-json: one JSON object per line, easy for
+# an experiment tracker to collect.
+class JsonLogFormatter(logging.Formatter):
+    def format(self, record):
+        return json.dumps({
+            'time': self.formatTime(record, '%Y-%m-%dT%H:%M:%S'),
+            'level': record.levelname.lower(),
+            'message': record.getMessage(),
+        })
+
+
+# This function configures logging from the global flags: default level INFO
+# (the per-command summaries), -v for DEBUG, --log-level to set it explicitly;
+# --log-json switches to one-object-per-line output. Logs go to stderr so
+# data written to stdout (stats, search results, reports) stays clean.
+def configure_logging(args):
+    if args.quiet:
+        level = logging.ERROR
+    elif args.log_level:
+        level = getattr(logging, args.log_level.upper())
+    else:
+        level = logging.DEBUG if args.verbose else logging.INFO
+    handler = logging.StreamHandler()
+    if args.log_json:
+        handler.setFormatter(JsonLogFormatter())
+    else:
+        handler.setFormatter(logging.Formatter('%(message)s'))
+    logging.basicConfig(level=level, handlers=[handler])
+
+
+# This function removes a value-taking global flag from sys.argv, used once
+# the flag has been folded into the recorded output paths.
+def _strip_argv_flag(flag):
+    argv = []
+    skip = False
+    for token in sys.argv:
+        if skip:
+            skip = False
+        elif token == flag:
+            skip = True
+        elif not token.startswith(flag + '='):
+            argv.append(token)
+    sys.argv = argv
+
+
+# This function renders an output filename from --name-template, keeping the
+# directory of the configured path. extra supplies per-file fields such as
+# epoch. Returns the path unchanged when no template is set.
+def render_output_name(args, path, **extra):
+    if not args.name_template:
+        return path
+    stem, ext = os.path.splitext(os.path.basename(path.rstrip('/')))
+    fields = {'stem': stem, 'ext': ext.lstrip('.'),
+              'command': args.command, 'variant': args.command,
+              'seed': getattr(args, 'seed', 0)}
+    fields.update(extra)
+    return os.path.join(os.path.dirname(path),
+                        args.name_template.format(**fields))
+
+
+# This function applies --suffix to every output path (inserted before the
+# extension, or appended for directories) and refuses to overwrite existing
+# outputs unless --force is given. Suffixed paths replace the originals in
+# sys.argv (and --suffix itself is dropped) so manifests and provenance
+# record the command that actually produces those files.
+def protect_outputs(args):
+    for name in manifest.OUTPUT_ARGS:
+        value = getattr(args, name, None)
+        if not isinstance(value, str):
+            continue
+        # Per-file fields ({epoch}) are rendered by the command itself.
+        if args.name_template and '{epoch}' not in args.name_template:
+            renamed = render_output_name(args, value)
+            if renamed != value:
+                sys.argv = [renamed if token == value else token
+                            for token in sys.argv]
+                setattr(args, name, renamed)
+                value = renamed
+        if args.suffix:
+            stem, ext = os.path.splitext(value.rstrip('/'))
+            renamed = '{}-{}{}'.format(stem, args.suffix, ext)
+            sys.argv = [renamed if token == value else token
+                        for token in sys.argv]
+            setattr(args, name, renamed)
+            value = renamed
+        if args.force:
+            continue
+        # A live checkpoint means this run is resuming into its own partial
+        # output, not clobbering someone else's.
+        checkpoint = getattr(args, 'checkpoint', None)
+        if isinstance(checkpoint, str) and os.path.isfile(checkpoint):
+            continue
+        if os.path.isdir(value):
+            if os.listdir(value):
+                logging.error('{} exists and is not empty; pass --force to '
+                              'overwrite or --suffix to version'.format(value))
+                sys.exit(EXIT_ERROR)
+        elif os.path.exists(value):
+            logging.error('{} exists; pass --force to overwrite or --suffix '
+                          'to version'.format(value))
+            sys.exit(EXIT_ERROR)
+    if args.suffix:
+        _strip_argv_flag('--suffix')
+    if args.name_template and '{epoch}' not in args.name_template:
+        _strip_argv_flag('--name-template')
+
+
+# This function splits the primary output into --shards contiguous, roughly
+# equal pieces named "<stem>-00001-of-0000N.json" and removes the single-file
+# output, the layout streaming dataloaders expect for large corpora.
+# Contiguous splitting keeps title grouping intact within each shard.
+def shard_output(args):
+    path = getattr(args, 'output', None)
+    if not isinstance(path, str) or not path.endswith('.json') \
+            or not os.path.isfile(path):
+        return
+    examples = list(read_raw_examples(path).values())
+    stem, ext = os.path.splitext(path)
+    base = len(examples) // args.shards
+    remainder = len(examples) % args.shards
+    position = 0
+    for index in range(args.shards):
+        size = base + (1 if index < remainder else 0)
+        shard_path = '{}-{:05d}-of-{:05d}{}'.format(
+            stem, index + 1, args.shards, ext)
+        write_squad_file(examples[position:position + size], shard_path)
+        position += size
+        logging.info('Shard {}/{}: {} examples -> {}'.format(
+            index + 1, args.shards, size, shard_path))
+    os.remove(path)
+
+
+# This function validates every SQuAD-format input file named by the args
+# against the expected schema, printing one "path<TAB>pointer<TAB>message"
+# line per violation so the list can be handed upstream as-is. JSON files
+# that are not SQuAD-shaped (score maps, manifests) are skipped. Exits with
+# EXIT_VALIDATION if anything is wrong.
+def check_strict_inputs(args):
+    num_violations = 0
+    for path in _input_files(args):
+        if not path.endswith('.json'):
+            continue
+        try:
+            with open(path, encoding='utf-8') as f:
+                raw = json.load(f)
+        except ValueError as error:
+            print('{}\t\tnot valid JSON: {}'.format(path, error))
+            num_violations += 1
+            continue
+        if not isinstance(raw, dict) or 'data' not in raw:
+            continue
+        for pointer, message in qa_data.validate_squad(raw):
+            print('{}\t{}\t{}'.format(path, pointer, message))
+            num_violations += 1
+    if num_violations:
+        logging.error('strict: {} schema violation(s)'.format(num_violations))
+        sys.exit(EXIT_VALIDATION)
+
+
+# This function lists the existing input files named by a command's
+# arguments (everything path-like that is not an output), for watch mode.
+def _input_files(args):
+    paths = []
+    for name, value in sorted(vars(args).items()):
+        if name in manifest.OUTPUT_ARGS or name in manifest.NON_INPUT_ARGS \
+                or name == 'watch':
+            continue
+        values = value if isinstance(value, list) else [value]
+        paths.extend(item for item in values
+                     if isinstance(item, str) and os.path.isfile(item))
+    return paths
+
+
+# This function runs one qabuild command in-process, e.g.
+#     qabuild.invoke(['synth', 'dev.json', '--entities', 'ents.tsv',
+#                     '-o', 'adv.json'])
+# It applies the same argument parsing, defaults, and provenance chaining as
+# the command line, and returns the parsed argument namespace.
+def invoke(argv):
+    argp, _ = build_parser()
+    args = argp.parse_args(argv)
+    args.func(args)
+    manifest.chain_provenance(args)
+    return args
+
+
+def main():
+    argp, _ = build_parser()
+    args = argp.parse_args()
+    configure_logging(args)
+    progress.set_enabled(False if args.quiet else args.progress)
+    qa_data.set_lenient(args.lenient)
+    qa_data.set_on_duplicate(args.on_duplicate)
+    qa_data.set_answer_policy(args.answers)
+    if args.track_memory:
+        tracemalloc.start()
+    protect_outputs(args)
+    if args.strict:
+        check_strict_inputs(args)
+    start = time.time()
+    try:
+        args.func(args)
+    except json.JSONDecodeError as error:
+        logging.error('parse error: {}'.format(error))
+        sys.exit(EXIT_PARSE)
+    except (ValueError, KeyError) as error:
+        logging.error('bad input data: {}'.format(error))
+        sys.exit(EXIT_PARSE)
+    except OSError as error:
+        logging.error(str(error))
+        sys.exit(EXIT_ERROR)
+    if args.shards > 1:
+        shard_output(args)
+    if args.track_memory:
+        _, python_peak = tracemalloc.get_traced_memory()
+        blocks = sum(stat.count for stat in
+                     tracemalloc.take_snapshot().statistics('filename'))
+        tracemalloc.stop()
+        # ru_maxrss is in KB on Linux.
+        rss_mb = resource.getrusage(resource.RUSAGE_SELF).ru_maxrss / 1024
+        run_summary['memory'] = collections.OrderedDict([
+            ('peak_rss_mb', round(rss_mb, 1)),
+            ('python_peak_mb', round(python_peak / (1 << 20), 1)),
+            ('live_blocks', blocks),
+        ])
+        logging.info('Memory: peak RSS {:.1f} MB, python peak {:.1f} MB, '
+                     '{} live blocks'.format(
+                         rss_mb, python_peak / (1 << 20), blocks))
+    if args.lenient and qa_data.REJECTS:
+        with open(args.rejects, encoding='utf-8', mode='w') as f:
+            for record in qa_data.REJECTS:
+                f.write(json.dumps(record, ensure_ascii=False) + '\n')
+        logging.warning('lenient: skipped {} malformed entries -> {}'.format(
+            len(qa_data.REJECTS), args.rejects))
+    manifest.chain_provenance(args)
+    if args.manifest:
+        manifest.write_run_manifest(args.manifest, args)
+        logging.info('Wrote manifest -> {}'.format(args.manifest))
+    if args.json_summary:
+        outputs = collections.OrderedDict()
+        for path in manifest.output_files(args):
+            entry = collections.OrderedDict()
+            if path.endswith('.json'):
+                try:
+                    entry['examples'] = len(read_raw_examples(path))
+                except (ValueError, KeyError, OSError):
+                    pass
+            outputs[path] = entry
+        summary = collections.OrderedDict([
+            ('command', args.command),
+            ('elapsed_seconds', round(time.time() - start, 3)),
+            ('inputs', _input_files(args)),
+            ('outputs', outputs),
+        ])
+        summary.update(run_summary)
+        text = json.dumps(summary, indent=2)
+        if args.json_summary == '-':
+            print(text)
+        else:
+            with open(args.json_summary, encoding='utf-8', mode='w') as f:
+                f.write(text + '\n')
+
+    if args.watch:
+        watched = _input_files(args)
+        snapshot = dict((path, os.path.getmtime(path)) for path in watched)
+        logging.info('Watching {} input file(s); Ctrl-C to stop'.format(len(watched)))
+        try:
+            while True:
+                time.sleep(1)
+                changed = [path for path in watched
+                           if os.path.isfile(path)
+                           and os.path.getmtime(path) != snapshot[path]]
+                if not changed:
+                    continue
+                for path in changed:
+                    snapshot[path] = os.path.getmtime(path)
+                logging.info('Changed: {}; rebuilding'.format(', '.join(changed)))
+                args.func(args)
+                manifest.chain_provenance(args)
+                if args.manifest:
+                    manifest.write_run_manifest(args.manifest, args)
+        except KeyboardInterrupt:
+            pass
+
+
+if __name__ == '__main__':
+    main()
@@ -0,0 +1,112 @@
+import collections
+import json
+import math
+import re
+
+# A small on-disk BM25 index over dataset paragraphs. This is the foundation
+# for hard-negative mining and retrieval-format exports: paragraphs are the
+# documents, and queries are typically questions. Kept in pure Python so index
+# builds run anywhere qabuild does.
+
+BM25_K1 = 1.5
+BM25_B = 0.75
+
+
+# Lowercased word tokenization shared by indexing and querying.
+def tokenize(text):
+    return re.findall(r'\w+', text.lower())
+
+
+# This function builds a BM25 index over the unique (title, context) paragraphs
+# of a dataset. The returned index dict is JSON-serializable; see save_index.
+def build_index(examples):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    paragraphs = []
+    seen = {}
+    for example in examples:
+        key = (example['title'], example['context'])
+        if key not in seen:
+            seen[key] = len(paragraphs)
+            paragraphs.append({'title': example['title'],
+                               'context': example['context']})
+
+    postings = collections.defaultdict(list)  # term -> [[doc_index, tf], ...]
+    doc_lens = []
+    for doc_index, paragraph in enumerate(paragraphs):
+        tokens = tokenize(paragraph['context'])
+        doc_lens.append(len(tokens))
+        counts = collections.Counter(tokens)
+        for term, tf in counts.items():
+            postings[term].append([doc_index, tf])
+
+    return {
+        'k1': BM25_K1,
+        'b': BM25_B,
+        'avgdl': sum(doc_lens) / len(doc_lens) if doc_lens else 0.0,
+        'doc_lens': doc_lens,
+        'paragraphs': paragraphs,
+        'postings': dict(postings),
+    }
+
+
+def save_index(index, path):
+    with open(path, encoding='utf-8', mode='w') as f:
+        json.dump(index, f, ensure_ascii=False)
+
+
+def load_index(path):
+    with open(path, encoding='utf-8') as f:
+        return json.load(f)
+
+
+# This function scores all indexed paragraphs against a query with BM25 and
+# returns the top-k as a list of (doc_index, score), best first.
+def search(index, query, k=10):
+    num_docs = len(index['doc_lens'])
+    if num_docs == 0:
+        return []
+    k1, b, avgdl = index['k1'], index['b'], index['avgdl']
+
+    scores = collections.defaultdict(float)
+    for term in set(tokenize(query)):
+        posting = index['postings'].get(term)
+        if not posting:
+            continue
+        idf = math.log(1 + (num_docs - len(posting) + 0.5) / (len(posting) + 0.5))
+        for doc_index, tf in posting:
+            denom = tf + k1 * (1 - b + b * index['doc_lens'][doc_index] / avgdl)
+            scores[doc_index] += idf * tf * (k1 + 1) / denom
+
+    ranked = sorted(scores.items(), key=lambda item: (-item[1], item[0]))
+    return ranked[:k]
+
+
+# This function mines BM25 hard negatives for each question: the top-k scoring
+# paragraphs that are not the question's own paragraph and do not contain any
+# gold answer string (case-insensitive), so a retriever trained on them never
+# sees a false negative.
+def mine_hard_negatives(examples, index, k=5):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    negatives = collections.OrderedDict()
+    for example in examples:
+        answers = [a['text'].lower() for a in example['answers']]
+        mined = []
+        # Over-retrieve, then filter down to k admissible paragraphs.
+        for doc_index, score in search(index, example['question'], k=k * 4 + 10):
+            paragraph = index['paragraphs'][doc_index]
+            if paragraph['context'] == example['context']:
+                continue
+            lowered = paragraph['context'].lower()
+            if any(answer and answer in lowered for answer in answers):
+                continue
+            mined.append({'title': paragraph['title'],
+                          'context': paragraph['context'],
+                          'score': score})
+            if len(mined) == k:
+                break
+        negatives[example['id']] = mined
+    return negatives
@@ -0,0 +1,338 @@
+import collections
+import csv
+import json
+import random
+
+from synth import WH_WORDS
+
+# Mixing and sampling logic for combining clean datasets with adversarial
+# variant datasets. Variant examples carry suffixed ids (e.g. "q1-addsent",
+# "q1-addsent-gaz2"); they are matched back to their base example by stripping
+# suffix segments until a clean id is found.
+
+
+# This function loads per-title fraction overrides from a TSV file with lines
+# of the form "title<TAB>fraction". Titles listed here get their own
+# adversarial fraction during mixing; everything else uses the global one.
+def load_title_fractions(path):
+    title_fractions = {}
+    with open(path, encoding='utf-8') as f:
+        for line in f:
+            line = line.rstrip('\n')
+            if not line or '\t' not in line:
+                continue
+            title, fraction = line.rsplit('\t', 1)
+            title_fractions[title] = float(fraction)
+    return title_fractions
+
+
+# This function loads per-example sampling weights from a TSV file with lines
+# of the form "id<TAB>weight". Ids not listed get weight 1.0 when sampling.
+def load_weights(path):
+    weights = {}
+    with open(path, encoding='utf-8') as f:
+        for line in f:
+            line = line.rstrip('\n')
+            if not line or '\t' not in line:
+                continue
+            example_id, weight = line.rsplit('\t', 1)
+            weights[example_id] = float(weight)
+    return weights
+
+
+# This function loads a per-id score file (IDFILE) mapping question id ->
+# score (typically F1). Three formats are autodetected:
+#   - a JSON object {"id": score, ...} (first non-space character is '{');
+#   - CSV with a header row naming an id column and a score column
+#     (first line contains a comma and a cell named like "id"/"qid");
+#   - plain TSV lines "id<TAB>score" (the default), where only the last tab
+#     splits so ids themselves may contain tabs-free punctuation freely.
+def read_idfile(path):
+    with open(path, encoding='utf-8') as f:
+        text = f.read()
+
+    stripped = text.lstrip()
+    if stripped.startswith('{'):
+        return collections.OrderedDict(
+            (example_id, float(score))
+            for example_id, score in json.loads(stripped).items())
+
+    lines = text.splitlines()
+    first = lines[0] if lines else ''
+    if ',' in first and any(cell.strip().lower() in ('id', 'qid', 'question_id')
+                            for cell in first.split(',')):
+        header = [cell.strip().lower() for cell in first.split(',')]
+        id_col = next(i for i, cell in enumerate(header)
+                      if cell in ('id', 'qid', 'question_id'))
+        score_col = next((i for i, cell in enumerate(header)
+                          if cell in ('f1', 'score', 'em')),
+                         1 if id_col == 0 else 0)
+        scores = collections.OrderedDict()
+        for row in csv.reader(lines[1:]):
+            if len(row) > max(id_col, score_col):
+                scores[row[id_col]] = float(row[score_col])
+        return scores
+
+    scores = collections.OrderedDict()
+    for line in lines:
+        if not line or '\t' not in line:
+            continue
+        example_id, score = line.rsplit('\t', 1)
+        scores[example_id] = float(score)
+    return scores
+
+
+# This function merges per-id scores from several model runs into one
+# consensus score per id: 'mean', 'min', or 'majority' (1.0 when at least half
+# the runs that scored the id put it at or above the threshold, else 0.0).
+# Ids missing from some runs are merged over the runs that have them.
+def ensemble_scores(score_maps, mode='mean', threshold=0.5):
+    merged = collections.OrderedDict()
+    for scores in score_maps:
+        for example_id, score in scores.items():
+            merged.setdefault(example_id, []).append(score)
+
+    consensus = collections.OrderedDict()
+    for example_id, values in merged.items():
+        if mode == 'min':
+            consensus[example_id] = min(values)
+        elif mode == 'majority':
+            correct = sum(1 for v in values if v >= threshold)
+            consensus[example_id] = 1.0 if correct * 2 >= len(values) else 0.0
+        else:
+            consensus[example_id] = sum(values) / len(values)
+    return consensus
+
+
+# This function assigns a score to a difficulty bucket given ascending edges.
+# The first edge is an inclusive upper bound — so an edge of 0 isolates
+# exactly-zero scores — and later edges are exclusive upper bounds: edges
+# [0, 0.5] yield the buckets F1=0, 0<F1<0.5, and F1>=0.5.
+def score_bucket(score, edges):
+    if score <= edges[0]:
+        return 0
+    for i in range(1, len(edges)):
+        if score < edges[i]:
+            return i
+    return len(edges)
+
+
+# This function draws up to per_bucket examples from each difficulty bucket
+# (uniformly, without replacement), so eval subsets are balanced by how hard
+# the current model finds each question. Examples whose id has no score are
+# ignored. Returns (sampled OrderedDict, per-bucket population counts).
+def stratified_sample(examples, scores, edges, per_bucket, rng):
+    buckets = [[] for _ in range(len(edges) + 1)]
+    for example_id in examples:
+        if example_id in scores:
+            buckets[score_bucket(scores[example_id], edges)].append(example_id)
+
+    chosen = set()
+    for bucket in buckets:
+        chosen.update(rng.sample(bucket, min(per_bucket, len(bucket))))
+
+    sampled = collections.OrderedDict()
+    for example_id, example in examples.items():
+        if example_id in chosen:
+            sampled[example_id] = example
+    return sampled, [len(bucket) for bucket in buckets]
+
+
+# This function draws `num` examples stratified by context length, given
+# ascending character-count bucket edges (same edge semantics as
+# score_bucket). Proportional mode (the default) splits `num` across
+# buckets by their population share (largest-remainder rounding), so a
+# subsample keeps the corpus length profile; uniform mode gives every
+# non-empty bucket the same quota, so long contexts aren't drowned out by
+# the short-paragraph bulk. Quotas are capped by bucket population, as in
+# stratified_sample. Returns (sampled OrderedDict, per-bucket population
+# counts).
+def length_balanced_sample(examples, edges, num, rng, uniform=False):
+    buckets = [[] for _ in range(len(edges) + 1)]
+    for example_id, example in examples.items():
+        buckets[score_bucket(len(example['context']), edges)].append(
+            example_id)
+    populations = [len(bucket) for bucket in buckets]
+    total = sum(populations)
+    if not total:
+        return collections.OrderedDict(), populations
+
+    if uniform:
+        nonempty = [i for i, population in enumerate(populations)
+                    if population]
+        base = num // len(nonempty)
+        extra = num - base * len(nonempty)
+        quotas = [0] * len(buckets)
+        for rank, i in enumerate(nonempty):
+            quotas[i] = min(base + (1 if rank < extra else 0),
+                            populations[i])
+    else:
+        raw = [num * population / total for population in populations]
+        quotas = [int(quota) for quota in raw]
+        leftover = num - sum(quotas)
+        by_remainder = sorted(range(len(buckets)),
+                              key=lambda i: quotas[i] - raw[i])
+        for i in by_remainder[:leftover]:
+            quotas[i] += 1
+        quotas = [min(quota, population)
+                  for quota, population in zip(quotas, populations)]
+
+    chosen = set()
+    for bucket, quota in zip(buckets, quotas):
+        chosen.update(rng.sample(bucket, quota))
+
+    sampled = collections.OrderedDict()
+    for example_id, example in examples.items():
+        if example_id in chosen:
+            sampled[example_id] = example
+    return sampled, populations
+
+
+# This function classifies a question by its first wh-word ("who", "what",
+# "when", ...), scanning past leading clauses ("In what year ..."); questions
+# with no wh-word at all ("Did the war end?") are typed 'other'.
+def question_type(question):
+    for token in question.lower().split():
+        token = token.strip('.,?!;:"\'')
+        if token in WH_WORDS:
+            return token
+    return 'other'
+
+
+# This function draws up to per_type examples per question type (uniformly,
+# without replacement), so adversarial subsets skewed toward "what"
+# questions don't distort evaluation. Returns (sampled OrderedDict,
+# per-type population counts); input order is preserved in the output.
+def type_balanced_sample(examples, per_type, rng):
+    buckets = collections.OrderedDict()
+    for example_id, example in examples.items():
+        buckets.setdefault(question_type(example['question']),
+                           []).append(example_id)
+
+    chosen = set()
+    for bucket in buckets.values():
+        chosen.update(rng.sample(bucket, min(per_type, len(bucket))))
+
+    sampled = collections.OrderedDict()
+    for example_id, example in examples.items():
+        if example_id in chosen:
+            sampled[example_id] = example
+    counts = collections.OrderedDict(
+        (qtype, len(bucket)) for qtype, bucket in buckets.items())
+    return sampled, counts
+
+
+# This function draws `num` examples without replacement, with inclusion
+# probability proportional to each example's weight (Efraimidis-Spirakis
+# reservoir keys: rank by rng.random() ** (1/weight)). Zero/negative weights
+# exclude an example outright; with no weights file every example weighs 1.0
+# and this degrades to a uniform sample. Input order is preserved in the output.
+def weighted_sample(examples, weights, num, rng):
+    keyed = []
+    for example_id, example in examples.items():
+        weight = weights.get(example_id, 1.0)
+        if weight <= 0:
+            continue
+        keyed.append((rng.random() ** (1.0 / weight), example_id))
+    keyed.sort(key=lambda item: -item[0])
+    chosen = set(example_id for _, example_id in keyed[:num])
+
+    sampled = collections.OrderedDict()
+    for example_id, example in examples.items():
+        if example_id in chosen:
+            sampled[example_id] = example
+    return sampled
+
+
+# This function groups variant examples by the clean base id they derive from.
+# Returns an OrderedDict base_id -> [variant example, ...]; variants whose id
+# matches no clean id are ignored.
+def match_variants(clean, adversarial):
+    if isinstance(adversarial, dict):
+        adversarial = adversarial.values()
+
+    matched = collections.OrderedDict()
+    for variant in adversarial:
+        candidate = variant['id']
+        while candidate not in clean and '-' in candidate:
+            candidate = candidate.rsplit('-', 1)[0]
+        if candidate in clean:
+            matched.setdefault(candidate, []).append(variant)
+    return matched
+
+
+# This function derives the per-eligible-example replacement probability
+# that makes roughly `target` of the OUTPUT adversarial, by counting how
+# many clean examples actually have variants in this input. The naive
+# approach of passing the target straight to get_append_examples silently
+# undershoots whenever coverage is partial (the probability only applies to
+# eligible examples), and hard-coding one dataset's counts doesn't
+# generalize. Returns (probability, num_eligible, max_attainable); the
+# probability is capped at 1.0, so callers should warn when target exceeds
+# max_attainable.
+def derive_fraction(clean, adversarial, target):
+    num_eligible = len(match_variants(clean, adversarial))
+    if not clean or not num_eligible:
+        return 0.0, num_eligible, 0.0
+    max_attainable = num_eligible / len(clean)
+    return (min(target / max_attainable, 1.0), num_eligible,
+            max_attainable)
+
+
+# This function mixes a clean dataset with adversarial variants: each clean
+# example that has at least one variant is replaced by a randomly chosen
+# variant with probability `fraction`; all other examples stay clean. By
+# default the variant is inserted under the base id so downstream joins keep
+# working; with keep_variant_ids the suffixed id is preserved for
+# traceability. Returns (mixed, base_id -> chosen variant_id mapping) — the
+# mapping records which ids were replaced either way.
+def get_append_examples(clean, adversarial, fraction, rng,
+                        keep_variant_ids=False, title_fractions=None):
+    matched = match_variants(clean, adversarial)
+
+    mixed = collections.OrderedDict()
+    mapping = collections.OrderedDict()
+    for example_id, example in clean.items():
+        variants = matched.get(example_id)
+        example_fraction = fraction
+        if title_fractions is not None:
+            example_fraction = title_fractions.get(example['title'], fraction)
+        if variants and rng.random() < example_fraction:
+            variant = dict(rng.choice(variants))
+            mapping[example_id] = variant['id']
+            if not keep_variant_ids:
+                variant['id'] = example_id
+            mixed[variant['id']] = variant
+        else:
+            mixed[example_id] = example
+    return mixed, mapping
+
+
+# This function generates a curriculum series: one mixed dataset per requested
+# adversarial fraction, all derived from the same seed (each stage gets its own
+# RNG stream so stages are independent draws). Returns a list of
+# (fraction, mixed_examples, num_adversarial) tuples in the given order.
+def curriculum_series(clean, adversarial, fractions, seed):
+    stages = []
+    for stage_index, fraction in enumerate(fractions):
+        rng = random.Random('{}-{}'.format(seed, stage_index))
+        mixed, mapping = get_append_examples(clean, adversarial, fraction, rng)
+        stages.append((fraction, mixed, len(mapping)))
+    return stages
+
+
+# Union mode: instead of choosing either the clean or the adversarial version
+# per id, keep both — every clean example plus every matched variant (under its
+# suffixed id), doubling coverage for training regimes that want the full union
+# rather than a sampled mix.
+def get_union_examples(clean, adversarial):
+    matched = match_variants(clean, adversarial)
+
+    union = collections.OrderedDict()
+    num_adversarial = 0
+    for example_id, example in clean.items():
+        union[example_id] = example
+        for variant in matched.get(example_id, []):
+            union[variant['id']] = variant
+            num_adversarial += 1
+    return union, num_adversarial
@@ -0,0 +1,85 @@
+# Rule-based sentence segmentation shared by the insertion, ablation, and
+# stats features. The previous per-module heuristics ("a sentence ends at
+# '.!?' before whitespace", "the distractor is everything after the last
+# boundary") broke on abbreviations ("Dr. Smith"), initials ("J. R. R.
+# Tolkien"), and quoted speech; this module is the one place those rules
+# live. Still deliberately dependency-free: no model downloads, works on
+# any machine qabuild runs on.
+
+# Lowercased abbreviations whose trailing period does not end a sentence.
+# Multi-dot abbreviations are matched against the whole preceding token
+# ("e.g." yields the token "e.g").
+ABBREVIATIONS = frozenset([
+    'mr', 'mrs', 'ms', 'dr', 'prof', 'rev', 'gen', 'col', 'sgt', 'st',
+    'jr', 'sr', 'vs', 'etc', 'e.g', 'i.e', 'cf', 'ca', 'approx', 'no',
+    'nos', 'fig', 'figs', 'vol', 'vols', 'ch', 'sec', 'pp', 'p', 'ed',
+    'eds', 'dept', 'univ', 'inc', 'ltd', 'co', 'corp', 'u.s', 'u.k',
+    'u.n', 'a.m', 'p.m', 'jan', 'feb', 'mar', 'apr', 'jun', 'jul', 'aug',
+    'sep', 'sept', 'oct', 'nov', 'dec',
+])
+
+# Closing quotes and brackets that belong to the sentence they terminate.
+_CLOSERS = '"\'”’)]'
+
+
+# This function returns the token immediately preceding position i (the
+# characters back to the previous whitespace, leading quotes/brackets
+# stripped), used to recognize abbreviations.
+def _preceding_token(text, i):
+    start = i
+    while start > 0 and not text[start - 1].isspace():
+        start -= 1
+    return text[start:i].lstrip('("\'“‘[')
+
+
+# This function splits text into sentence spans [(start, end), ...]. A
+# sentence ends at a run of '.', '!' or '?' (plus any closing quotes or
+# brackets) followed by whitespace or end of text — unless the terminator
+# is a lone period after a known abbreviation or a single initial.
+# Decimals ("3.14") never split because the period is not followed by
+# whitespace. Whitespace between sentences belongs to neither span.
+def split_sentences(text):
+    spans = []
+    start = 0
+    i = 0
+    n = len(text)
+    while i < n:
+        if text[i] not in '.!?':
+            i += 1
+            continue
+        # Extend over terminator runs ("...", "?!") and trailing closers.
+        end = i
+        while end + 1 < n and text[end + 1] in '.!?':
+            end += 1
+        while end + 1 < n and text[end + 1] in _CLOSERS:
+            end += 1
+        if end + 1 < n and not text[end + 1].isspace():
+            i = end + 1
+            continue
+        if text[i] == '.' and end == i:
+            token = _preceding_token(text, i)
+            if (token.lower() in ABBREVIATIONS
+                    or (len(token) == 1 and token.isalpha()
+                        and token.isupper())):
+                i += 1
+                continue
+        # A following lowercase word means the quote or terminator sits
+        # mid-sentence ('"Hello!" he said.').
+        rest = text[end + 1:].lstrip()
+        if rest and rest[0].islower():
+            i = end + 1
+            continue
+        spans.append((start, end + 1))
+        i = end + 1
+        while i < n and text[i].isspace():
+            i += 1
+        start = i
+    if start < n and text[start:].strip():
+        spans.append((start, n))
+    return spans
+
+
+# This function splits text into sentence strings, for callers that don't
+# need offsets.
+def split_sentence_texts(text):
+    return [text[s:e] for s, e in split_sentences(text)]
@@ -0,0 +1,299 @@
+import collections
+import itertools
+import re
+import string
+import unicodedata
+
+import segment
+
+# Dataset statistics for qabuild. Length statistics are computed in characters
+# by default; pass a tokenizer to also get subword token counts, which is what
+# actually matters for transformer input limits (character limits keep letting
+# through contexts that overflow 512 tokens). Deliberately dependency-free so
+# qabuild stays usable on machines without the training stack installed.
+
+
+# This function loads a tokenizer for token-level statistics and filters.
+# Accepts either a path to a tokenizer.json file (loaded with the `tokenizers`
+# library) or a HuggingFace model ID / checkpoint directory (loaded with
+# AutoTokenizer). Imports are deferred so the rest of qabuild works without
+# the tokenizer dependencies installed.
+def load_tokenizer(name_or_path):
+    if name_or_path.endswith('.json'):
+        from tokenizers import Tokenizer
+        tokenizer = Tokenizer.from_file(name_or_path)
+        return lambda text: len(tokenizer.encode(text).ids)
+    else:
+        from transformers import AutoTokenizer
+        tokenizer = AutoTokenizer.from_pretrained(name_or_path, use_fast=True)
+        return lambda text: len(tokenizer(text)['input_ids'])
+
+
+def _percentile(sorted_lengths, p):
+    index = min(int(round(p / 100.0 * (len(sorted_lengths) - 1))),
+                len(sorted_lengths) - 1)
+    return sorted_lengths[index]
+
+
+def _length_summary(lengths):
+    lengths = sorted(lengths)
+    return {
+        'min': lengths[0],
+        'mean': sum(lengths) / len(lengths),
+        'p50': _percentile(lengths, 50),
+        'p95': _percentile(lengths, 95),
+        'max': lengths[-1],
+    }
+
+
+# This function computes summary statistics over a dataset: example/title/
+# context counts, answerability, and question/context length distributions.
+# If count_tokens (a callable from load_tokenizer) is given, token-level
+# lengths are reported too, including how many question+context pairs would
+# overflow `token_limit` tokens.
+def compute_stats(examples, count_tokens=None, token_limit=512):
+    if isinstance(examples, dict):
+        examples = examples.values()
+    examples = list(examples)
+
+    contexts = collections.OrderedDict()
+    for example in examples:
+        contexts.setdefault(example['context'], 0)
+
+    result = collections.OrderedDict()
+    result['num_examples'] = len(examples)
+    result['num_titles'] = len(set(e['title'] for e in examples))
+    result['num_contexts'] = len(contexts)
+    result['num_impossible'] = sum(1 for e in examples if e.get('is_impossible'))
+    if not examples:
+        return result
+
+    result['question_chars'] = _length_summary(
+        [len(e['question']) for e in examples])
+    result['context_chars'] = _length_summary(
+        [len(c) for c in contexts])
+    result['context_sentences'] = _length_summary(
+        [len(segment.split_sentences(c)) for c in contexts])
+
+    if count_tokens is not None:
+        question_tokens = [count_tokens(e['question']) for e in examples]
+        context_token_cache = {c: count_tokens(c) for c in contexts}
+        context_tokens = [context_token_cache[e['context']] for e in examples]
+        result['question_tokens'] = _length_summary(question_tokens)
+        result['context_tokens'] = _length_summary(list(context_token_cache.values()))
+        result['over_token_limit'] = sum(
+            1 for q, c in zip(question_tokens, context_tokens)
+            if q + c > token_limit)
+        result['token_limit'] = token_limit
+    return result
+
+
+# The answer normalization steps the scorer applies before comparing, each
+# an independent toggle: English SQuAD wants all four, but numeric-answer
+# sets should keep punctuation (decimal points) and non-English sets have no
+# articles to strip.
+NORMALIZE_STEPS = ('lowercase', 'articles', 'punctuation', 'unicode')
+
+
+# This function returns the callable the scorer splits answers with, so F1
+# matches whichever eval convention a paper used: 'whitespace' splits on
+# whitespace (the SQuAD official script), 'regex' extracts \w+ runs (the
+# Unicode-friendly convention of several multilingual eval scripts), and any
+# other value is loaded as a subword tokenizer (a tokenizer.json file or a
+# HuggingFace model ID, as in load_tokenizer) so scores line up with the
+# model's own vocabulary.
+def load_token_splitter(mode):
+    if mode == 'whitespace':
+        return str.split
+    if mode == 'regex':
+        return re.compile(r'\w+', re.UNICODE).findall
+    if mode.endswith('.json'):
+        from tokenizers import Tokenizer
+        tokenizer = Tokenizer.from_file(mode)
+        return lambda text: tokenizer.encode(text).tokens
+    else:
+        from transformers import AutoTokenizer
+        tokenizer = AutoTokenizer.from_pretrained(mode, use_fast=True)
+        return tokenizer.tokenize
+
+
+# This function normalizes an answer string by the selected steps:
+# 'unicode' folds to NFKD and drops combining marks, 'lowercase' lowers,
+# 'punctuation' replaces punctuation with spaces, 'articles' drops a/an/the.
+# Whitespace is always collapsed.
+def normalize_answer(text, steps=NORMALIZE_STEPS):
+    if 'unicode' in steps:
+        text = unicodedata.normalize('NFKD', text)
+        text = ''.join(ch for ch in text if not unicodedata.combining(ch))
+    if 'lowercase' in steps:
+        text = text.lower()
+    if 'punctuation' in steps:
+        text = ''.join(' ' if ch in string.punctuation else ch
+                       for ch in text)
+    tokens = text.split()
+    if 'articles' in steps:
+        tokens = [token for token in tokens
+                  if token not in ('a', 'an', 'the')]
+    return ' '.join(tokens)
+
+
+# This function computes SQuAD-style token-level F1 between two answer
+# strings (normalized tokens, bag-of-tokens overlap).
+def answer_f1(a, b, steps=NORMALIZE_STEPS, tokenize=None):
+    tokenize = tokenize or str.split
+    tokens_a = tokenize(normalize_answer(a, steps))
+    tokens_b = tokenize(normalize_answer(b, steps))
+    if not tokens_a or not tokens_b:
+        return float(tokens_a == tokens_b)
+    common = collections.Counter(tokens_a) & collections.Counter(tokens_b)
+    overlap = sum(common.values())
+    if overlap == 0:
+        return 0.0
+    precision = overlap / len(tokens_a)
+    recall = overlap / len(tokens_b)
+    return 2 * precision * recall / (precision + recall)
+
+
+# This function computes the character-range Jaccard overlap of two answer
+# spans.
+def _span_jaccard(a, b):
+    start_a = a['answer_start']
+    end_a = start_a + len(a['text'])
+    start_b = b['answer_start']
+    end_b = start_b + len(b['text'])
+    intersection = max(0, min(end_a, end_b) - max(start_a, start_b))
+    union = (end_a - start_a) + (end_b - start_b) - intersection
+    return intersection / union if union else 1.0
+
+
+# This function computes inter-annotator agreement over every question that
+# carries two or more gold answers: mean pairwise exact match, token F1, and
+# span Jaccard overlap, overall and per title. Returns (report,
+# per_question) where per_question maps id -> its three agreement values, so
+# low-agreement questions can be pulled for review.
+def compute_agreement(examples, steps=NORMALIZE_STEPS, tokenize=None):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    per_question = collections.OrderedDict()
+    titles = collections.OrderedDict()
+    num_questions = 0
+    for example in examples:
+        num_questions += 1
+        answers = example['answers']
+        if len(answers) < 2:
+            continue
+        pairs = list(itertools.combinations(answers, 2))
+        em = sum(normalize_answer(a['text'], steps)
+                 == normalize_answer(b['text'], steps)
+                 for a, b in pairs) / len(pairs)
+        f1 = sum(answer_f1(a['text'], b['text'], steps, tokenize)
+                 for a, b in pairs) / len(pairs)
+        overlap = sum(_span_jaccard(a, b) for a, b in pairs) / len(pairs)
+        per_question[example['id']] = collections.OrderedDict([
+            ('exact_match', em),
+            ('answer_f1', f1),
+            ('span_overlap', overlap),
+        ])
+        bucket = titles.setdefault(example['title'], {
+            'num_questions': 0, 'exact_match': 0.0,
+            'answer_f1': 0.0, 'span_overlap': 0.0})
+        bucket['num_questions'] += 1
+        bucket['exact_match'] += em
+        bucket['answer_f1'] += f1
+        bucket['span_overlap'] += overlap
+
+    report = collections.OrderedDict()
+    report['num_questions'] = num_questions
+    report['num_multi_annotated'] = len(per_question)
+    if per_question:
+        for key in ('exact_match', 'answer_f1', 'span_overlap'):
+            report[key] = sum(values[key]
+                              for values in per_question.values()) \
+                / len(per_question)
+        report['titles'] = collections.OrderedDict()
+        for title, bucket in titles.items():
+            count = bucket['num_questions']
+            report['titles'][title] = collections.OrderedDict([
+                ('num_questions', count),
+                ('exact_match', bucket['exact_match'] / count),
+                ('answer_f1', bucket['answer_f1'] / count),
+                ('span_overlap', bucket['span_overlap'] / count),
+            ])
+    return report, per_question
+
+
+# This function bins (confidence, correct) pairs into fixed-width
+# reliability bins and computes the expected calibration error: the
+# count-weighted mean |accuracy - mean confidence| over the bins. Empty bins
+# are kept in the output (with just their range and a zero count) so decile
+# tables line up across runs.
+def compute_calibration(records, num_bins=10):
+    bins = [{'lo': i / num_bins, 'hi': (i + 1) / num_bins,
+             'count': 0, 'correct': 0, 'confidence': 0.0}
+            for i in range(num_bins)]
+    for confidence, correct in records:
+        bucket = bins[min(int(confidence * num_bins), num_bins - 1)]
+        bucket['count'] += 1
+        bucket['correct'] += int(correct)
+        bucket['confidence'] += confidence
+
+    total = sum(bucket['count'] for bucket in bins)
+    report_bins = []
+    ece = 0.0
+    for bucket in bins:
+        entry = collections.OrderedDict([
+            ('lo', bucket['lo']),
+            ('hi', bucket['hi']),
+            ('count', bucket['count']),
+        ])
+        if bucket['count']:
+            accuracy = bucket['correct'] / bucket['count']
+            mean_confidence = bucket['confidence'] / bucket['count']
+            entry['accuracy'] = accuracy
+            entry['mean_confidence'] = mean_confidence
+            ece += bucket['count'] / total * abs(accuracy - mean_confidence)
+        report_bins.append(entry)
+    return collections.OrderedDict([
+        ('count', total),
+        ('ece', ece),
+        ('bins', report_bins),
+    ])
+
+
+# This function finds n-gram overlap between the contexts of a training set
+# and a held-out eval set: any training context sharing at least one n-gram
+# (lowercased whitespace tokens, n=13 by convention) with an eval context is
+# flagged. Returns an OrderedDict train id -> eval id of the first matching
+# passage. Contexts are checked once and shared by their examples, so
+# paragraph-heavy files don't re-scan the same passage per question.
+def find_contamination(train_examples, eval_examples, n=13):
+    if isinstance(train_examples, dict):
+        train_examples = train_examples.values()
+    if isinstance(eval_examples, dict):
+        eval_examples = eval_examples.values()
+
+    def ngrams(text):
+        tokens = text.lower().split()
+        return set(' '.join(tokens[i:i + n])
+                   for i in range(len(tokens) - n + 1))
+
+    eval_index = {}
+    for example in eval_examples:
+        for gram in ngrams(example['context']):
+            eval_index.setdefault(gram, example['id'])
+
+    context_hits = {}
+    contaminated = collections.OrderedDict()
+    for example in train_examples:
+        context = example['context']
+        if context not in context_hits:
+            hit = None
+            for gram in ngrams(context):
+                if gram in eval_index:
+                    hit = eval_index[gram]
+                    break
+            context_hits[context] = hit
+        if context_hits[context] is not None:
+            contaminated[example['id']] = context_hits[context]
+    return contaminated
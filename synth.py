@@ -0,0 +1,177 @@
+import collections
+import json
+
+# Rule-based AddSent-lite distractor synthesis.
+#
+# The original AddSent attack (Jia & Liang 2017) rewrote each question into a
+# declarative sentence whose answer entity was swapped for a decoy, then appended
+# it to the context. That pipeline required a parser and a crowdsourcing pass;
+# this module implements a rule-based approximation that only needs the question,
+# the gold answer, and a user-supplied entity list, so fresh adversarial data can
+# be generated for domains where no AddSent dump exists.
+
+# Wh-words we try to strip/replace when turning a question into a statement.
+WH_WORDS = ['who', 'whom', 'whose', 'what', 'which', 'when', 'where', 'why', 'how']
+
+# Leading auxiliaries that follow the wh-phrase and get dropped in the rewrite
+# ("what did X build" -> "X build <decoy>").
+AUX_WORDS = ['is', 'are', 'was', 'were', 'do', 'does', 'did', 'has', 'have', 'had',
+             'can', 'could', 'will', 'would', 'shall', 'should', 'may', 'might']
+
+
+# This function loads a gazetteer/entity list from a TSV file with lines of the
+# form "type<TAB>entity". Lines without a tab are assigned the type 'misc'.
+# Returns a dict mapping type -> list of entity strings.
+def load_entity_list(path):
+    entities = collections.OrderedDict()
+    with open(path, encoding='utf-8') as f:
+        for line in f:
+            line = line.rstrip('\n')
+            if not line:
+                continue
+            if '\t' in line:
+                ent_type, entity = line.split('\t', 1)
+            else:
+                ent_type, entity = 'misc', line
+            entities.setdefault(ent_type, []).append(entity)
+    return entities
+
+
+# Character-trigram similarity used for nearest-neighbor decoy selection.
+def _trigram_similarity(a, b):
+    def trigrams(s):
+        s = s.lower()
+        return set(s[i:i + 3] for i in range(max(len(s) - 2, 1)))
+    ta, tb = trigrams(a), trigrams(b)
+    if not ta or not tb:
+        return 0.0
+    return len(ta & tb) / len(ta | tb)
+
+
+# This function picks up to k decoy entities for the given answer: the most
+# similar entities of the same type that are not the answer itself. The
+# answer's type is taken to be the type whose list contains the answer
+# verbatim, falling back to scanning every type.
+def pick_decoys(answer, entities, k=1):
+    answer_lower = answer.lower()
+
+    candidate_types = [ent_type for ent_type, ents in entities.items()
+                       if any(e.lower() == answer_lower for e in ents)]
+    if not candidate_types:
+        candidate_types = list(entities.keys())
+
+    scored = []
+    for ent_type in candidate_types:
+        for entity in entities[ent_type]:
+            if entity.lower() == answer_lower:
+                continue
+            scored.append((_trigram_similarity(answer, entity), entity))
+    scored.sort(key=lambda item: -item[0])
+    return [entity for _, entity in scored[:k]]
+
+
+def pick_decoy(answer, entities):
+    decoys = pick_decoys(answer, entities, k=1)
+    return decoys[0] if decoys else None
+
+
+# This function converts a question into a declarative distractor sentence with
+# the decoy substituted where the wh-phrase was. The rules are deliberately
+# simple: find the wh-word, drop a following auxiliary if present, splice the
+# remaining question words back together, and attach the decoy at the wh-site.
+def question_to_distractor(question, decoy):
+    tokens = question.rstrip().rstrip('?').split()
+    if not tokens:
+        return None
+    lower = [t.lower() for t in tokens]
+
+    wh_index = None
+    for i, t in enumerate(lower):
+        if t in WH_WORDS:
+            wh_index = i
+            break
+
+    if wh_index is None:
+        # No wh-word found (e.g. "Name the ..."): just state the decoy at the end.
+        rest = tokens
+        sentence_tokens = rest + [decoy]
+    else:
+        rest = tokens[:wh_index] + tokens[wh_index + 1:]
+        # Drop an auxiliary that immediately followed the wh-word.
+        if wh_index < len(rest) and rest[wh_index].lower() in AUX_WORDS:
+            rest = rest[:wh_index] + rest[wh_index + 1:]
+        if lower[wh_index] in ('when', 'where', 'why'):
+            # "When did X happen" -> "X happen in <decoy>"
+            sentence_tokens = rest[wh_index:] + ['in', decoy]
+        else:
+            # "What did X build" -> "X build <decoy>"; leading wh keeps statement order.
+            sentence_tokens = rest[wh_index:] + [decoy]
+        sentence_tokens = tokens[:wh_index] + sentence_tokens if wh_index > 0 else sentence_tokens
+
+    sentence = ' '.join(sentence_tokens).strip()
+    if not sentence:
+        return None
+    return sentence[0].upper() + sentence[1:] + '.'
+
+
+# This function loads a distractor sentence bank: JSONL lines with at least a
+# 'sentence' key (plus optional 'title'/'source_id' metadata).
+def load_distractor_bank(path):
+    bank = []
+    with open(path, encoding='utf-8') as f:
+        for line in f:
+            if line.strip():
+                bank.append(json.loads(line))
+    return bank
+
+
+# This function generates one adversarial example per input example by
+# inserting num_distractors synthesized sentences into the context, either
+# appended (offsets unchanged) or prepended (every answer_start shifted by the
+# inserted length, in characters, so spans survive on any script). Distractor
+# sentences come from decoy-swapped question rewrites, or are sampled from a
+# distractor bank when one is supplied (sentences containing a gold answer are
+# never sampled). Examples for which no distractor can be produced are
+# skipped. Returns an OrderedDict of new examples keyed by their suffixed ids.
+def synth_distractor_examples(examples, entities=None, id_suffix='addsent',
+                              position='append', num_distractors=1,
+                              bank=None, rng=None):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    synthesized = collections.OrderedDict()
+    for example in examples:
+        if not example['answers']:
+            continue
+        answer = example['answers'][0]['text']
+
+        distractors = []
+        if bank is not None:
+            gold = [a['text'].lower() for a in example['answers']]
+            candidates = [entry['sentence'] for entry in bank
+                          if not any(g and g in entry['sentence'].lower()
+                                     for g in gold)]
+            rng.shuffle(candidates)
+            distractors = candidates[:num_distractors]
+        else:
+            for decoy in pick_decoys(answer, entities, k=num_distractors):
+                distractor = question_to_distractor(example['question'], decoy)
+                if distractor is not None and distractor not in distractors:
+                    distractors.append(distractor)
+        if not distractors:
+            continue
+
+        new_example = dict(example)
+        new_example['id'] = '{}-{}'.format(example['id'], id_suffix)
+        inserted = ' '.join(distractors)
+        if position == 'prepend':
+            shift = len(inserted) + 1
+            new_example['context'] = inserted + ' ' + example['context']
+            new_example['answers'] = [{'text': a['text'],
+                                       'answer_start': a['answer_start'] + shift}
+                                      for a in example['answers']]
+        else:
+            new_example['context'] = example['context'].rstrip() + ' ' + inserted
+            new_example['answers'] = [dict(a) for a in example['answers']]
+        synthesized[new_example['id']] = new_example
+    return synthesized
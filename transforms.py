@@ -0,0 +1,597 @@
+import collections
+import re
+import unicodedata
+
+from segment import split_sentences
+
+# Context-level transforms for qabuild: sentence shuffling and the ablation
+# modes used to build diagnostic eval sets. Unlike the augment transforms,
+# these rewrite the whole dataset (keeping original ids) rather than emitting
+# extra variant examples, so predictions can be joined against the originals.
+
+
+# This function finds the index of the sentence span fully containing the given
+# answer, or None if the answer crosses a sentence boundary.
+def _answer_sentence(spans, answer):
+    ans_start = answer['answer_start']
+    ans_end = ans_start + len(answer['text'])
+    for i, (start, end) in enumerate(spans):
+        if start <= ans_start and ans_end <= end:
+            return i
+    return None
+
+
+# This function rebuilds a context from an ordered list of sentence strings and
+# remaps answers given a map from old sentence index -> new sentence index.
+# Returns (new_context, new_answer_starts_by_old_sentence_offset).
+def _rebuild_context(sentences, order):
+    new_context = ' '.join(sentences[i] for i in order)
+    offsets = {}
+    pos = 0
+    for i in order:
+        offsets[i] = pos
+        pos += len(sentences[i]) + 1
+    return new_context, offsets
+
+
+# Sentence-shuffle robustness transform. The order of context sentences is
+# shuffled (sentence text, including the answer sentence, stays intact) and all
+# answer offsets are recomputed. Examples whose answers cross sentence
+# boundaries are passed through unchanged.
+def shuffle_sentences_examples(examples, rng):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    out = collections.OrderedDict()
+    for example in examples:
+        context = example['context']
+        spans = split_sentences(context)
+        sentence_indices = [_answer_sentence(spans, a) for a in example['answers']]
+        if len(spans) < 2 or any(i is None for i in sentence_indices):
+            out[example['id']] = example
+            continue
+
+        sentences = [context[s:e] for s, e in spans]
+        order = list(range(len(sentences)))
+        rng.shuffle(order)
+        new_context, offsets = _rebuild_context(sentences, order)
+
+        new_example = dict(example)
+        new_example['context'] = new_context
+        new_example['answers'] = []
+        for answer, sent_index in zip(example['answers'], sentence_indices):
+            relative = answer['answer_start'] - spans[sent_index][0]
+            new_example['answers'].append({
+                'text': answer['text'],
+                'answer_start': offsets[sent_index] + relative,
+            })
+        out[new_example['id']] = new_example
+    return out
+
+
+# Answer-sentence-only ablation. Each context is reduced to just the sentence
+# containing the (first) gold answer, with offsets recomputed, to measure how
+# much models rely on surrounding context. Examples whose answers cannot be
+# mapped into a single sentence are dropped.
+def answer_sentence_only_examples(examples):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    out = collections.OrderedDict()
+    for example in examples:
+        if not example['answers']:
+            continue
+        context = example['context']
+        spans = split_sentences(context)
+        sent_index = _answer_sentence(spans, example['answers'][0])
+        if sent_index is None:
+            continue
+        sent_start, sent_end = spans[sent_index]
+
+        new_answers = []
+        for answer in example['answers']:
+            if _answer_sentence(spans, answer) == sent_index:
+                new_answers.append({
+                    'text': answer['text'],
+                    'answer_start': answer['answer_start'] - sent_start,
+                })
+        if not new_answers:
+            continue
+
+        new_example = dict(example)
+        new_example['context'] = context[sent_start:sent_end]
+        new_example['answers'] = new_answers
+        out[new_example['id']] = new_example
+    return out
+
+
+# No-answer-sentence ablation. The answer-bearing sentence is removed from the
+# context, answers are cleared, and the example is marked is_impossible,
+# generating SQuAD 2.0-style unanswerable examples from answerable ones.
+# Examples that are already unanswerable or whose answer crosses sentence
+# boundaries are dropped.
+def drop_answer_sentence_examples(examples):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    out = collections.OrderedDict()
+    for example in examples:
+        if not example['answers']:
+            continue
+        context = example['context']
+        spans = split_sentences(context)
+        if len(spans) < 2:
+            continue
+        answer_sentences = set()
+        for answer in example['answers']:
+            sent_index = _answer_sentence(spans, answer)
+            if sent_index is None:
+                answer_sentences = None
+                break
+            answer_sentences.add(sent_index)
+        if answer_sentences is None or len(answer_sentences) >= len(spans):
+            continue
+
+        sentences = [context[s:e] for i, (s, e) in enumerate(spans)
+                     if i not in answer_sentences]
+        new_example = dict(example)
+        new_example['context'] = ' '.join(sentences)
+        new_example['answers'] = []
+        new_example['is_impossible'] = True
+        out[new_example['id']] = new_example
+    return out
+
+
+# Partial-input baseline modes. 'context-only' blanks the question (answers and
+# offsets are untouched); 'question-only' blanks the context, which necessarily
+# clears the answers and marks examples is_impossible since no span can exist.
+# These are the baselines reviewers ask for alongside adversarial numbers.
+def partial_input_examples(examples, keep):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    out = collections.OrderedDict()
+    for example in examples:
+        new_example = dict(example)
+        if keep == 'context':
+            new_example['question'] = ''
+            new_example['answers'] = [dict(a) for a in example['answers']]
+        elif keep == 'question':
+            new_example['context'] = ''
+            new_example['answers'] = []
+            new_example['is_impossible'] = True
+        else:
+            raise ValueError('keep must be "context" or "question", got {!r}'.format(keep))
+        out[new_example['id']] = new_example
+    return out
+
+
+# Answer-centered context truncation. Contexts longer than max_chars are cut to
+# a window centered on the (first) answer span so long paragraphs fit small
+# models without losing the answer; offsets are recomputed and answers that
+# fall outside the window are dropped. Unanswerable examples keep their prefix.
+def truncate_context_examples(examples, max_chars):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    out = collections.OrderedDict()
+    for example in examples:
+        context = example['context']
+        if len(context) <= max_chars:
+            out[example['id']] = example
+            continue
+
+        if example['answers']:
+            answer = example['answers'][0]
+            ans_start = answer['answer_start']
+            ans_end = ans_start + len(answer['text'])
+            margin = max((max_chars - (ans_end - ans_start)) // 2, 0)
+            start = max(ans_start - margin, 0)
+            end = min(start + max_chars, len(context))
+            start = max(end - max_chars, 0)
+        else:
+            start, end = 0, max_chars
+
+        new_answers = []
+        for answer in example['answers']:
+            a_start = answer['answer_start']
+            a_end = a_start + len(answer['text'])
+            if start <= a_start and a_end <= end:
+                new_answers.append({'text': answer['text'],
+                                    'answer_start': a_start - start})
+        if example['answers'] and not new_answers:
+            continue
+
+        new_example = dict(example)
+        new_example['context'] = context[start:end]
+        new_example['answers'] = new_answers
+        out[new_example['id']] = new_example
+    return out
+
+
+# Per-title long-context concatenation. All paragraphs belonging to a title
+# are joined (in first-seen order) into one long context shared by every
+# question of that title, with answer offsets shifted by the position of the
+# question's original paragraph. Produces a long-context variant of a dataset.
+def concat_title_examples(examples, separator='\n'):
+    if isinstance(examples, dict):
+        examples = examples.values()
+    examples = list(examples)
+
+    # First pass: collect each title's contexts in order and their offsets in
+    # the concatenated text.
+    title_contexts = collections.OrderedDict()
+    for example in examples:
+        contexts = title_contexts.setdefault(example['title'], collections.OrderedDict())
+        if example['context'] not in contexts:
+            contexts[example['context']] = None
+
+    title_concat = {}
+    title_offsets = {}
+    for title, contexts in title_contexts.items():
+        offsets = {}
+        pos = 0
+        parts = []
+        for context in contexts:
+            offsets[context] = pos
+            parts.append(context)
+            pos += len(context) + len(separator)
+        title_concat[title] = separator.join(parts)
+        title_offsets[title] = offsets
+
+    out = collections.OrderedDict()
+    for example in examples:
+        shift = title_offsets[example['title']][example['context']]
+        new_example = dict(example)
+        new_example['context'] = title_concat[example['title']]
+        new_example['answers'] = [{'text': a['text'],
+                                   'answer_start': a['answer_start'] + shift}
+                                  for a in example['answers']]
+        out[new_example['id']] = new_example
+    return out
+
+
+# Sliding-window feature chunking. Long contexts are split into overlapping
+# chunks of max_len characters advancing by stride, mirroring the HF squad
+# preprocessing but done once at build time. Answers are mapped into every
+# chunk that fully contains them; chunks without the answer are emitted as
+# unanswerable (is_impossible) so they can serve as negatives or be filtered.
+def chunk_examples(examples, max_len, stride, keep_empty=True):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    out = collections.OrderedDict()
+    for example in examples:
+        context = example['context']
+        if len(context) <= max_len:
+            out[example['id']] = example
+            continue
+
+        start = 0
+        chunk_index = 0
+        while True:
+            end = min(start + max_len, len(context))
+            new_answers = []
+            for answer in example['answers']:
+                a_start = answer['answer_start']
+                a_end = a_start + len(answer['text'])
+                if start <= a_start and a_end <= end:
+                    new_answers.append({'text': answer['text'],
+                                        'answer_start': a_start - start})
+            if new_answers or keep_empty:
+                new_example = dict(example)
+                new_example['id'] = '{}-chunk{}'.format(example['id'], chunk_index)
+                new_example['context'] = context[start:end]
+                new_example['answers'] = new_answers
+                if not new_answers:
+                    new_example['is_impossible'] = True
+                out[new_example['id']] = new_example
+                chunk_index += 1
+            if end == len(context):
+                break
+            start += stride
+    return out
+
+
+# Unanswerable-negative generation by context swapping. Each question is paired
+# with a different context from the same title that does not contain any gold
+# answer string (case-insensitive), and the pair is marked is_impossible. This
+# bulks up SQuAD 2.0-style negatives with plausible but wrong contexts.
+def context_swap_negatives(examples, rng, id_suffix='noans'):
+    if isinstance(examples, dict):
+        examples = examples.values()
+    examples = list(examples)
+
+    title_contexts = collections.OrderedDict()
+    for example in examples:
+        contexts = title_contexts.setdefault(example['title'], [])
+        if example['context'] not in contexts:
+            contexts.append(example['context'])
+
+    out = collections.OrderedDict()
+    for example in examples:
+        answers = [a['text'].lower() for a in example['answers']]
+        candidates = [c for c in title_contexts[example['title']]
+                      if c != example['context']
+                      and not any(answer and answer in c.lower()
+                                  for answer in answers)]
+        if not candidates:
+            continue
+        new_example = dict(example)
+        new_example['id'] = '{}-{}'.format(example['id'], id_suffix)
+        new_example['context'] = rng.choice(candidates)
+        new_example['answers'] = []
+        new_example['is_impossible'] = True
+        out[new_example['id']] = new_example
+    return out
+
+
+# This function recovers the sentence inserted into an adversarial context by
+# diffing it against the base context: the common prefix and suffix are found
+# at character level, and the remainder is the insertion. This works for
+# prepended, appended, or mid-context insertions and is safe for any script,
+# unlike slicing by length arithmetic (which assumed the insertion lay beyond
+# len(base) and corrupted offsets on multi-byte text). Returns (start, end)
+# of the inserted region in adv_context, or None if adv is not base plus one
+# insertion.
+def extract_insertion(base_context, adv_context):
+    if len(adv_context) < len(base_context):
+        return None
+
+    prefix = 0
+    max_prefix = min(len(base_context), len(adv_context))
+    while prefix < max_prefix and base_context[prefix] == adv_context[prefix]:
+        prefix += 1
+
+    suffix = 0
+    max_suffix = min(len(base_context), len(adv_context)) - prefix
+    while (suffix < max_suffix
+           and base_context[len(base_context) - 1 - suffix]
+           == adv_context[len(adv_context) - 1 - suffix]):
+        suffix += 1
+
+    start, end = prefix, len(adv_context) - suffix
+    if base_context[:prefix] + base_context[len(base_context) - suffix:] \
+            != adv_context[:start] + adv_context[end:]:
+        return None
+    return start, end
+
+
+# This function shifts a character offset to account for an insertion of
+# `length` characters at `insert_start`: offsets at or past the insertion
+# point move right, earlier offsets are unchanged.
+def shift_offset(offset, insert_start, length):
+    return offset + length if offset >= insert_start else offset
+
+
+# Unicode normalization pass. Contexts and questions are normalized to the
+# requested form (NFC/NFKC) and answer offsets are remapped by normalizing the
+# context prefix up to each span boundary — mixed-normalization inputs
+# otherwise cause silent span misalignment downstream. Answers whose text no
+# longer matches the remapped slice are re-located near the expected position
+# when possible and dropped otherwise.
+def normalize_unicode_examples(examples, form='NFC'):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    out = collections.OrderedDict()
+    for example in examples:
+        context = example['context']
+        new_context = unicodedata.normalize(form, context)
+
+        new_answers = []
+        for answer in example['answers']:
+            new_text = unicodedata.normalize(form, answer['text'])
+            new_start = len(unicodedata.normalize(
+                form, context[:answer['answer_start']]))
+            if new_context[new_start:new_start + len(new_text)] != new_text:
+                # The boundary fell inside a combining sequence; search nearby.
+                window_start = max(new_start - 4, 0)
+                found = new_context.find(new_text, window_start,
+                                         new_start + len(new_text) + 4)
+                if found == -1:
+                    continue
+                new_start = found
+            new_answers.append({'text': new_text, 'answer_start': new_start})
+
+        new_example = dict(example)
+        new_example['context'] = new_context
+        new_example['question'] = unicodedata.normalize(form, example['question'])
+        new_example['answers'] = new_answers
+        out[new_example['id']] = new_example
+    return out
+
+
+# Whitespace normalization. Runs of whitespace (including newlines) in the
+# context are collapsed to a single space and leading/trailing whitespace is
+# stripped, with every answer_start adjusted through an old->new index map.
+# Each remapped span is verified to still match its (whitespace-collapsed)
+# answer text; answers that fail verification are dropped.
+def collapse_whitespace_examples(examples):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    out = collections.OrderedDict()
+    for example in examples:
+        context = example['context']
+        new_chars = []
+        index_map = {}
+        for i, ch in enumerate(context):
+            if ch.isspace():
+                if new_chars and new_chars[-1] != ' ':
+                    index_map[i] = len(new_chars)
+                    new_chars.append(' ')
+                else:
+                    # Collapsed away; map to the position of the kept space.
+                    index_map[i] = max(len(new_chars) - 1, 0)
+            else:
+                index_map[i] = len(new_chars)
+                new_chars.append(ch)
+        # Strip a trailing space left by trailing whitespace.
+        if new_chars and new_chars[-1] == ' ':
+            new_chars.pop()
+        new_context = ''.join(new_chars)
+        index_map[len(context)] = len(new_context)
+
+        new_answers = []
+        for answer in example['answers']:
+            new_text = ' '.join(answer['text'].split())
+            new_start = index_map[answer['answer_start']]
+            if new_context[new_start:new_start + len(new_text)] == new_text:
+                new_answers.append({'text': new_text, 'answer_start': new_start})
+
+        new_example = dict(example)
+        new_example['context'] = new_context
+        new_example['question'] = ' '.join(example['question'].split())
+        new_example['answers'] = new_answers
+        out[new_example['id']] = new_example
+    return out
+
+
+# PII patterns for anonymize_examples. The phone pattern deliberately
+# over-matches (any 7+ digit run with common separators) because a leaked
+# number costs more than a mangled product code.
+EMAIL_RE = re.compile(r'\b[\w.+-]+@[\w-]+(?:\.[\w-]+)+\b')
+PHONE_RE = re.compile(r'\+?\d[\d ()./-]{5,}\d')
+
+
+# This function finds the PII spans in a piece of text: emails, phone-like
+# digit runs, and word-boundary occurrences of the supplied person names
+# (longest first, so full names win over surnames). Overlapping matches keep
+# the first claimant. Returns a sorted list of (start, end, type, value).
+def _pii_spans(text, names):
+    candidates = []
+    for m in EMAIL_RE.finditer(text):
+        candidates.append((m.start(), m.end(), 'email', m.group()))
+    for m in PHONE_RE.finditer(text):
+        if sum(ch.isdigit() for ch in m.group()) >= 7:
+            candidates.append((m.start(), m.end(), 'phone', m.group()))
+    for name in sorted(names, key=len, reverse=True):
+        for m in re.finditer(r'\b' + re.escape(name) + r'\b', text):
+            candidates.append((m.start(), m.end(), 'person', name))
+
+    claimed = [False] * len(text)
+    spans = []
+    for start, end, pii_type, value in candidates:
+        if any(claimed[start:end]):
+            continue
+        for i in range(start, end):
+            claimed[i] = True
+        spans.append((start, end, pii_type, value))
+    spans.sort()
+    return spans
+
+
+# PII anonymization: emails, phone-number-like strings, and person names
+# (from a caller-supplied list) are replaced with typed placeholders like
+# "[PERSON-2]" in both contexts and questions, with answer offsets remapped.
+# The same value maps to the same placeholder everywhere in the dataset, so
+# coreference across paragraphs survives. An answer span that overlaps
+# redacted text becomes the placeholder itself rather than leaking a
+# fragment of it. Ids are kept, per this module's convention. Returns
+# (examples, mapping) where mapping records value -> placeholder for an
+# audit trail that must NOT be shared alongside the output.
+def anonymize_examples(examples, names=()):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    mapping = collections.OrderedDict()
+    counters = collections.Counter()
+
+    def placeholder(pii_type, value):
+        key = (pii_type, value)
+        if key not in mapping:
+            counters[pii_type] += 1
+            mapping[key] = '[{}-{}]'.format(pii_type.upper(),
+                                            counters[pii_type])
+        return mapping[key]
+
+    def redact(text):
+        spans = [(start, end, placeholder(pii_type, value))
+                 for start, end, pii_type, value in _pii_spans(text, names)]
+        pieces = []
+        cursor = 0
+        for start, end, replacement in spans:
+            pieces.append(text[cursor:start])
+            pieces.append(replacement)
+            cursor = end
+        pieces.append(text[cursor:])
+        return ''.join(pieces), spans
+
+    out = collections.OrderedDict()
+    for example in examples:
+        new_context, spans = redact(example['context'])
+
+        new_answers = []
+        for answer in example['answers']:
+            a_start = answer['answer_start']
+            a_end = a_start + len(answer['text'])
+            shift = sum(len(replacement) - (end - start)
+                        for start, end, replacement in spans
+                        if end <= a_start)
+            overlapping = [(start, end, replacement)
+                           for start, end, replacement in spans
+                           if start < a_end and end > a_start]
+            if overlapping:
+                start, end, replacement = overlapping[0]
+                new_start = start + sum(
+                    len(r) - (e - s) for s, e, r in spans if e <= start)
+                new_answers.append({'text': replacement,
+                                    'answer_start': new_start})
+            else:
+                new_answers.append({'text': answer['text'],
+                                    'answer_start': a_start + shift})
+
+        new_example = dict(example)
+        new_example['context'] = new_context
+        new_example['question'] = redact(example['question'])[0]
+        new_example['answers'] = new_answers
+        out[new_example['id']] = new_example
+    return out, collections.OrderedDict(
+        (value, mapping[(pii_type, value)]) for pii_type, value in mapping)
+
+
+# Answer-length filters. Raw annotation dumps reliably contain one-character
+# "answers" (a stray click) and whole-sentence spans (a drag too far); both
+# are noise worth excluding at build time. Answers outside the given
+# char/token bounds (None bounds are open; tokens are whitespace tokens)
+# are dropped, and an answerable example that loses all its answers is
+# dropped with them — is_impossible examples pass through untouched.
+# Returns (filtered, num_answers_dropped, num_examples_dropped).
+def filter_answer_length_examples(examples, min_chars=None, max_chars=None,
+                                  min_tokens=None, max_tokens=None):
+    if isinstance(examples, dict):
+        examples = examples.values()
+
+    def keep(text):
+        if min_chars is not None and len(text) < min_chars:
+            return False
+        if max_chars is not None and len(text) > max_chars:
+            return False
+        tokens = len(text.split())
+        if min_tokens is not None and tokens < min_tokens:
+            return False
+        if max_tokens is not None and tokens > max_tokens:
+            return False
+        return True
+
+    out = collections.OrderedDict()
+    num_answers_dropped = 0
+    num_examples_dropped = 0
+    for example in examples:
+        if example.get('is_impossible'):
+            out[example['id']] = example
+            continue
+        kept = [answer for answer in example['answers']
+                if keep(answer['text'])]
+        num_answers_dropped += len(example['answers']) - len(kept)
+        if not kept:
+            num_examples_dropped += 1
+            continue
+        if len(kept) == len(example['answers']):
+            out[example['id']] = example
+            continue
+        new_example = dict(example)
+        new_example['answers'] = kept
+        out[new_example['id']] = new_example
+    return out, num_answers_dropped, num_examples_dropped
@@ -0,0 +1,137 @@
+import curses
+import textwrap
+
+import sampling
+
+# Terminal dataset browser: a searchable example list on the left, the
+# selected example on the right with its answer spans highlighted in the
+# context, and — when an adversarial companion file is loaded — the matched
+# variant rendered below the clean example for side-by-side inspection.
+#
+# Keys: up/down/pgup/pgdn move, '/' starts an id/question substring filter
+# (Enter applies, Esc clears), 'q' quits.
+
+LIST_WIDTH = 28
+
+
+# This function renders one example into (line, attr-spans) pairs: the
+# question, a blank line, then the wrapped context with answer spans marked
+# for highlighting.
+def _layout_example(example, width):
+    lines = [('Q: ' + example['question'], [])]
+    if example.get('is_impossible'):
+        lines.append(('(unanswerable)', []))
+    lines.append(('', []))
+
+    spans = []
+    for answer in example['answers']:
+        start = answer['answer_start']
+        spans.append((start, start + len(answer['text'])))
+
+    context = example['context']
+    position = 0
+    for line in textwrap.wrap(context, width, drop_whitespace=False) or ['']:
+        line_start = context.find(line, position)
+        if line_start == -1:
+            line_start = position
+        position = line_start + len(line)
+        highlights = []
+        for span_start, span_end in spans:
+            lo = max(span_start, line_start) - line_start
+            hi = min(span_end, position) - line_start
+            if lo < hi:
+                highlights.append((lo, hi))
+        lines.append((line, highlights))
+    return lines
+
+
+# This function draws a laid-out example into the window at the given row,
+# applying reverse video to the highlight spans. Returns the next free row.
+def _draw_example(window, row, lines, width, max_row):
+    for line, highlights in lines:
+        if row >= max_row:
+            break
+        window.addnstr(row, LIST_WIDTH + 2, line, width)
+        for lo, hi in highlights:
+            window.addnstr(row, LIST_WIDTH + 2 + lo, line[lo:hi],
+                           width - lo, curses.A_REVERSE)
+        row += 1
+    return row
+
+
+def _main(stdscr, examples, compare):
+    curses.curs_set(0)
+    matched = sampling.match_variants(examples, compare) if compare else {}
+    selected = 0
+    top = 0
+    query = ''
+    typing = False
+
+    while True:
+        ids = [example_id for example_id in examples
+               if query.lower() in example_id.lower()
+               or query.lower() in examples[example_id]['question'].lower()]
+        selected = max(0, min(selected, len(ids) - 1))
+
+        height, width = stdscr.getmaxyx()
+        pane_width = max(10, width - LIST_WIDTH - 3)
+        if selected < top:
+            top = selected
+        if selected >= top + height - 2:
+            top = selected - height + 3
+
+        stdscr.erase()
+        status = '/' + query if (typing or query) else ''
+        stdscr.addnstr(0, 0, '{} examples  {}'.format(len(ids), status),
+                       width - 1, curses.A_BOLD)
+        for row, example_id in enumerate(ids[top:top + height - 2]):
+            attr = curses.A_REVERSE if top + row == selected else 0
+            stdscr.addnstr(row + 1, 0, example_id.ljust(LIST_WIDTH),
+                           LIST_WIDTH, attr)
+        for row in range(1, height - 1):
+            stdscr.addch(row, LIST_WIDTH + 1, curses.ACS_VLINE)
+
+        if ids:
+            example = examples[ids[selected]]
+            lines = _layout_example(example, pane_width)
+            row = _draw_example(stdscr, 1, lines, pane_width, height - 1)
+            variants = matched.get(ids[selected])
+            if variants and row + 2 < height - 1:
+                stdscr.addnstr(row, LIST_WIDTH + 2,
+                               '--- variant: {} ---'.format(variants[0]['id']),
+                               pane_width, curses.A_BOLD)
+                _draw_example(stdscr, row + 1,
+                              _layout_example(variants[0], pane_width),
+                              pane_width, height - 1)
+        stdscr.refresh()
+
+        key = stdscr.getch()
+        if typing:
+            if key in (curses.KEY_ENTER, 10, 13):
+                typing = False
+            elif key == 27:
+                typing = False
+                query = ''
+            elif key in (curses.KEY_BACKSPACE, 127, 8):
+                query = query[:-1]
+            elif 32 <= key < 127:
+                query += chr(key)
+            continue
+        if key in (ord('q'), 27):
+            break
+        elif key == ord('/'):
+            typing = True
+            query = ''
+        elif key == curses.KEY_UP:
+            selected -= 1
+        elif key == curses.KEY_DOWN:
+            selected += 1
+        elif key == curses.KEY_PPAGE:
+            selected -= height - 3
+        elif key == curses.KEY_NPAGE:
+            selected += height - 3
+
+
+# This function runs the browser until the user quits.
+def browse(examples, compare=None):
+    curses.wrapper(_main, examples, compare)